@@ -0,0 +1,173 @@
+//! Runs a seeded random edit workload against a rope, checking it against a
+//! plain `String` along the way, and reports how long the edits took.
+//!
+//!     cargo run --release --example stress -- --seed 7 --ops 100000 --size 1000000
+
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+
+use rope::{Rope, MAX_DEPTH};
+
+struct Config {
+    seed: u64,
+    ops: usize,
+    size: usize,
+    check_every: usize,
+}
+
+fn parse_args() -> Config {
+    let mut config = Config {
+        seed: 1,
+        ops: 10_000,
+        size: 100_000,
+        check_every: 1000,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().and_then(|v| v.parse::<u64>().ok());
+        match (flag.as_str(), value) {
+            ("--seed", Some(v)) => config.seed = v,
+            ("--ops", Some(v)) => config.ops = v as usize,
+            ("--size", Some(v)) => config.size = v as usize,
+            ("--check-every", Some(v)) => config.check_every = (v as usize).max(1),
+            _ => {
+                eprintln!("usage: stress [--seed N] [--ops N] [--size BYTES] [--check-every N]");
+                process::exit(2);
+            }
+        }
+    }
+    config
+}
+
+// xorshift64*, good enough to drive edits and reproducible from the seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+}
+
+const SAMPLES: &[&str] = &["a", "hello ", "\n", "é", "日本語", "\t", "🦀", "line\r\n"];
+
+fn random_text(rng: &mut Rng, max_len: usize) -> String {
+    let mut s = String::new();
+    while s.len() < max_len {
+        s.push_str(SAMPLES[rng.below(SAMPLES.len())]);
+    }
+    s
+}
+
+// a random char boundary of `s`
+fn boundary(rng: &mut Rng, s: &str) -> usize {
+    let mut at = rng.below(s.len() + 1);
+    while !s.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+fn check(rope: &Rope, model: &str, op: usize) {
+    let fail = |what: &str| {
+        eprintln!("invariant violated after op {}: {}", op, what);
+        process::exit(1);
+    };
+    if rope.len_bytes() != model.len() {
+        fail("byte length");
+    }
+    if rope.len_chars() != model.chars().count() {
+        fail("char length");
+    }
+    if rope.len_lines() != model.matches('\n').count() + 1 {
+        fail("line count");
+    }
+    if rope.len_utf16() != model.encode_utf16().count() {
+        fail("UTF-16 length");
+    }
+    let longest = model.split('\n').map(|line| line.chars().count()).max();
+    if Some(rope.longest_line()) != longest {
+        fail("longest line");
+    }
+    if rope.depth() > MAX_DEPTH {
+        fail("tree depth");
+    }
+    if !rope
+        .chunks()
+        .flat_map(str::as_bytes)
+        .eq(model.as_bytes().iter())
+    {
+        fail("content");
+    }
+}
+
+fn main() {
+    let config = parse_args();
+    let mut rng = Rng::new(config.seed);
+
+    let mut model = random_text(&mut rng, config.size);
+    let mut rope = Rope::new("");
+    rope.insert(0, &model);
+
+    let (mut inserts, mut removes) = (0, 0);
+    let (mut edit_time, mut check_time) = (Duration::default(), Duration::default());
+    for op in 1..=config.ops {
+        // lean towards inserts while below the target size
+        let insert =
+            model.is_empty() || rng.below(100) < if model.len() < config.size { 60 } else { 40 };
+        if insert {
+            let at = boundary(&mut rng, &model);
+            let len = rng.below(64) + 1;
+            let text = random_text(&mut rng, len);
+            let started = Instant::now();
+            rope.insert(at, &text);
+            edit_time += started.elapsed();
+            model.insert_str(at, &text);
+            inserts += 1;
+        } else {
+            let start = boundary(&mut rng, &model);
+            let mut end = (start + rng.below(128)).min(model.len());
+            while !model.is_char_boundary(end) {
+                end -= 1;
+            }
+            let started = Instant::now();
+            rope.remove(start..end);
+            edit_time += started.elapsed();
+            model.replace_range(start..end, "");
+            removes += 1;
+        }
+
+        if op % config.check_every == 0 || op == config.ops {
+            let started = Instant::now();
+            check(&rope, &model, op);
+            check_time += started.elapsed();
+        }
+    }
+
+    let per_op = edit_time.as_nanos() / config.ops.max(1) as u128;
+    println!(
+        "seed {}: {} inserts, {} removes",
+        config.seed, inserts, removes
+    );
+    println!(
+        "final size {} bytes, {} lines, depth {}",
+        rope.len_bytes(),
+        rope.len_lines(),
+        rope.depth()
+    );
+    println!(
+        "edits {:?} ({} ns/op), checks {:?}",
+        edit_time, per_op, check_time
+    );
+}
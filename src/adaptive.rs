@@ -0,0 +1,182 @@
+use std::ops::Range;
+
+use crate::buffer::TextBuffer;
+use crate::builder::RopeBuilder;
+use crate::gap_buffer::{Gap, PROMOTE_LEN};
+use crate::rope::Rope;
+
+// chunked ropes shrinking below this size move back into a gap buffer
+const DEMOTE_LEN: usize = PROMOTE_LEN / 4;
+
+// edits further than this many chars from the previous one count as a jump
+const JUMP_DISTANCE: usize = 4096;
+
+// consecutive jumps after which a gap buffer is abandoned for a rope
+const MAX_JUMPS: u32 = 8;
+
+/// The storage currently backing an `AdaptiveBuffer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Gap,
+    Leaf,
+    Chunked,
+}
+
+#[derive(Debug)]
+enum State {
+    Gap(Gap),
+    Leaf(Rope),
+    Chunked(Rope),
+}
+
+/// A text buffer that migrates between storages as the document evolves:
+///
+/// * freshly loaded documents that fit `PROMOTE_LEN` sit in a single-leaf
+///   rope, which costs nothing to build and is contiguous for reads,
+/// * small documents being edited move to a gap buffer, as long as edits
+///   stay close to each other,
+/// * large documents and scattered edit patterns use a chunked rope.
+#[derive(Debug)]
+pub struct AdaptiveBuffer {
+    state: State,
+    last_edit: usize,
+    jumps: u32,
+}
+
+impl AdaptiveBuffer {
+    pub fn new(s: &str) -> AdaptiveBuffer {
+        let state = if s.len() > PROMOTE_LEN {
+            State::Chunked(chunked(s.chars()))
+        } else {
+            State::Leaf(Rope::new(s))
+        };
+        AdaptiveBuffer {
+            state,
+            last_edit: 0,
+            jumps: 0,
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self.state {
+            State::Gap(_) => Backend::Gap,
+            State::Leaf(_) => Backend::Leaf,
+            State::Chunked(_) => Backend::Chunked,
+        }
+    }
+
+    // records an edit at `offset` and picks the storage for it
+    fn before_edit(&mut self, offset: usize) {
+        if offset.abs_diff(self.last_edit) > JUMP_DISTANCE {
+            self.jumps += 1;
+        } else {
+            self.jumps = 0;
+        }
+        self.last_edit = offset;
+
+        let next = match &self.state {
+            State::Leaf(rope) if rope.len() <= PROMOTE_LEN => {
+                Some(State::Gap(Gap::new(&rope.chars().collect::<String>())))
+            }
+            State::Leaf(rope) => Some(State::Chunked(chunked(rope.chars()))),
+            State::Gap(gap) if self.jumps >= MAX_JUMPS => {
+                Some(State::Chunked(chunked(gap.chars())))
+            }
+            State::Chunked(rope) if self.jumps == 0 && rope.len() < DEMOTE_LEN => {
+                Some(State::Gap(Gap::new(&rope.chars().collect::<String>())))
+            }
+            _ => None,
+        };
+        if let Some(state) = next {
+            self.state = state;
+        }
+    }
+
+    fn after_edit(&mut self) {
+        if let State::Gap(gap) = &self.state {
+            if gap.len_bytes() > PROMOTE_LEN {
+                self.state = State::Chunked(chunked(gap.chars()));
+            }
+        }
+    }
+}
+
+fn chunked(chars: impl Iterator<Item = char>) -> Rope {
+    let mut builder = RopeBuilder::new();
+    let mut buf = [0; 4];
+    for c in chars {
+        builder.append(c.encode_utf8(&mut buf));
+    }
+    builder.finish()
+}
+
+impl TextBuffer for AdaptiveBuffer {
+    fn len_chars(&self) -> usize {
+        match &self.state {
+            State::Gap(gap) => gap.len(),
+            State::Leaf(rope) | State::Chunked(rope) => rope.len_chars(),
+        }
+    }
+
+    fn insert_str(&mut self, offset: usize, s: &str) {
+        self.before_edit(offset);
+        match &mut self.state {
+            State::Gap(gap) => gap.insert(offset, s),
+            State::Leaf(rope) | State::Chunked(rope) => rope.insert_str(offset, s),
+        }
+        self.after_edit();
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) {
+        self.before_edit(range.start);
+        match &mut self.state {
+            State::Gap(gap) => gap.remove(range),
+            State::Leaf(rope) | State::Chunked(rope) => rope.remove_range(range),
+        }
+        self.after_edit();
+    }
+
+    fn slice_to_string(&self, range: Range<usize>) -> String {
+        match &self.state {
+            State::Gap(gap) => gap
+                .chars()
+                .skip(range.start)
+                .take(range.end.saturating_sub(range.start))
+                .collect(),
+            State::Leaf(rope) | State::Chunked(rope) => TextBuffer::slice_to_string(rope, range),
+        }
+    }
+}
+
+#[test]
+fn test_adaptive_small_document() {
+    let mut buffer = AdaptiveBuffer::new("Hello, World!");
+    assert_eq!(buffer.backend(), Backend::Leaf);
+
+    buffer.insert_str(6, " Cruel");
+    assert_eq!(buffer.backend(), Backend::Gap);
+    assert_eq!(buffer.contents(), "Hello, Cruel World!");
+}
+
+#[test]
+fn test_adaptive_scattered_edits() {
+    let text = "abcdefgh\n".repeat(2048);
+    let mut buffer = AdaptiveBuffer::new(&text);
+    buffer.insert_str(0, "x");
+    assert_eq!(buffer.backend(), Backend::Gap);
+
+    for i in 0..MAX_JUMPS as usize {
+        let offset = if i % 2 == 0 { 10_000 } else { 10 };
+        buffer.insert_str(offset, "y");
+    }
+    assert_eq!(buffer.backend(), Backend::Chunked);
+    assert_eq!(buffer.len_chars(), text.len() + 1 + MAX_JUMPS as usize);
+}
+
+#[test]
+fn test_adaptive_large_document() {
+    let text = "x".repeat(PROMOTE_LEN + 1);
+    let buffer = AdaptiveBuffer::new(&text);
+    assert_eq!(buffer.backend(), Backend::Chunked);
+    assert_eq!(buffer.len_chars(), PROMOTE_LEN + 1);
+}
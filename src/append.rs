@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::rope::{Rope, MAX_LEAF_LEN};
+
+impl Rope {
+    /// Appends `s` at the end. Small strings are merged into the last leaf
+    /// while it has room, so growing a buffer a few chars at a time doesn't
+    /// leave one leaf per call behind.
+    pub fn push_str(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if let Some(merged) = self.merge_edge(s, true) {
+            *self = merged;
+            return;
+        }
+        *self = Rope::concat(self.share(), Rope::chunked(s));
+    }
+
+    /// Appends `other` at the end, sharing its leaves unless it is small
+    /// enough to be merged into the last leaf.
+    pub fn append(&mut self, other: Rope) {
+        if let Rope::Leaf(leaf) = &other {
+            if leaf.start == leaf.end {
+                return;
+            }
+            if let Some(merged) = self.merge_edge(leaf.as_str(), true) {
+                *self = merged;
+                return;
+            }
+        }
+        *self = Rope::concat(self.share(), other);
+    }
+
+    /// Inserts `other` at the start, sharing its leaves unless it is small
+    /// enough to be merged into the first leaf.
+    pub fn prepend(&mut self, other: Rope) {
+        if let Rope::Leaf(leaf) = &other {
+            if leaf.start == leaf.end {
+                return;
+            }
+            if let Some(merged) = self.merge_edge(leaf.as_str(), false) {
+                *self = merged;
+                return;
+            }
+        }
+        *self = Rope::concat(other, self.share());
+    }
+
+    /// A rope holding the text `n` times over, like `str::repeat`. All the
+    /// copies share the same subtree, so this takes O(log n) time and memory
+    /// on top of the rope itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result would be longer than `Rope::MAX_LEN`.
+    pub fn repeat(&self, n: usize) -> Rope {
+        let len = self.len().checked_mul(n);
+        assert!(
+            len.is_some_and(|len| len <= Rope::MAX_LEN),
+            "repeated rope too long"
+        );
+        if n == 0 || self.is_empty() {
+            return Rope::empty();
+        }
+        let unit = Arc::new(self.share());
+        let mut built = HashMap::new();
+        Rope::repeated(&unit, n, &mut built).share()
+    }
+
+    // a balanced tree of `n` copies of `unit`. Both halves of a tree hold
+    // either the same number of copies or one apart, so there are at most
+    // two distinct trees per level to build
+    fn repeated(unit: &Arc<Rope>, n: usize, built: &mut HashMap<usize, Arc<Rope>>) -> Arc<Rope> {
+        if n == 1 {
+            return Arc::clone(unit);
+        }
+        if let Some(rope) = built.get(&n) {
+            return Arc::clone(rope);
+        }
+        let left = Rope::repeated(unit, n / 2, built);
+        let right = Rope::repeated(unit, n - n / 2, built);
+        let rope = Arc::new(Rope::join(left, right));
+        built.insert(n, Arc::clone(&rope));
+        rope
+    }
+
+    // the rope with `s` merged into its last (or first) leaf, if that leaf
+    // has room for it; the rest of the tree is shared
+    fn merge_edge(&self, s: &str, back: bool) -> Option<Rope> {
+        match self {
+            Rope::Leaf(leaf) => {
+                if leaf.end - leaf.start + s.len() > MAX_LEAF_LEN {
+                    return None;
+                }
+                let text = match back {
+                    true => leaf.as_str().to_string() + s,
+                    false => s.to_string() + leaf.as_str(),
+                };
+                Some(Rope::new(&text))
+            }
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let right = node.right.as_ref().expect("right child cannot be empty");
+                if back {
+                    let right = right.merge_edge(s, back)?;
+                    return Some(Rope::join(Arc::clone(left), Arc::new(right)));
+                }
+                let left = left.merge_edge(s, back)?;
+                Some(Rope::join(Arc::new(left), Arc::clone(right)))
+            }
+        }
+    }
+}
+
+/// Appends formatted text with `write!`. The pieces are merged into the last
+/// leaf like with `push_str`, so formatting many small values still fills
+/// leaves up to their size.
+impl fmt::Write for Rope {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_push_str() {
+    let mut rope = Rope::empty();
+    for i in 0..1000 {
+        rope.push_str(&format!("{},", i % 10));
+    }
+    assert_eq!(rope.len(), 2000);
+    assert_eq!(rope.chunks().count(), 2);
+    assert!(rope.chars().collect::<String>().starts_with("0,1,2,"));
+
+    let big = "x".repeat(3 * MAX_LEAF_LEN);
+    rope.push_str(&big);
+    assert_eq!(rope.len(), 2000 + big.len());
+    assert!(rope.chunks().all(|chunk| chunk.len() <= MAX_LEAF_LEN));
+}
+
+#[test]
+fn test_write_fmt() {
+    use std::fmt::Write;
+
+    let mut rope = Rope::new("log:\n");
+    for n in 0..200 {
+        writeln!(rope, "line {}: ok", n).unwrap();
+    }
+    rope.write_char('é').unwrap();
+    assert!(rope
+        .to_string()
+        .starts_with("log:\nline 0: ok\nline 1: ok\n"));
+    assert!(rope.to_string().ends_with("line 199: ok\né"));
+    assert_eq!(rope.len_lines(), 202);
+    assert_eq!(rope.chunks().count(), rope.len().div_ceil(MAX_LEAF_LEN));
+}
+
+#[test]
+fn test_append_and_prepend() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("b"), Rope::new("c")]);
+    let original = rope.share();
+
+    rope.append(Rope::new("d"));
+    rope.prepend(Rope::new("a"));
+    assert_eq!(rope.chars().collect::<String>(), "abcd");
+    assert_eq!(rope.chunks().count(), 2);
+
+    rope.append(Rope::from_leaves(vec![Rope::new("e"), Rope::new("f")]));
+    rope.prepend(Rope::empty());
+    assert_eq!(rope.chars().collect::<String>(), "abcdef");
+    assert_eq!(original.chars().collect::<String>(), "bc");
+
+    // a large leaf is shared, not copied
+    let big = Rope::new(&"x".repeat(1 << 20));
+    rope.append(big.share());
+    assert!(rope.shares_subtree(&big));
+    let mut empty = Rope::empty();
+    empty.append(big.share());
+    assert!(empty.shares_subtree(&big));
+}
+
+#[test]
+fn test_repeat() {
+    let rope = Rope::new("ab\n");
+
+    let repeated = rope.repeat(5);
+    assert_eq!(repeated.chars().collect::<String>(), "ab\n".repeat(5));
+    assert_eq!(repeated.len_lines(), 6);
+    assert!(rope.repeat(0).is_empty());
+
+    // a million copies share one leaf under a shallow tree
+    let huge = rope.repeat(1_000_000);
+    assert_eq!(huge.len(), 3_000_000);
+    assert_eq!(huge.depth(), 20);
+    assert_eq!(huge.chars_at(2_999_999).next(), Some('\n'));
+}
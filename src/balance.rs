@@ -0,0 +1,133 @@
+use std::fmt;
+
+use crate::rope::Rope;
+
+/// Depth past which new nodes rebalance the tree, keeping recursive
+/// traversals far from the stack limit even on adversarial edit sequences.
+pub const MAX_DEPTH: usize = 64;
+
+/// What `guard_depth` does with a rope deeper than allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthPolicy {
+    Rebalance,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthError {
+    pub depth: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for DepthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rope depth {} exceeds limit {}", self.depth, self.limit)
+    }
+}
+
+impl std::error::Error for DepthError {}
+
+impl Rope {
+    /// Height of the tree; a single leaf has depth 0. Cached per node.
+    pub fn depth(&self) -> usize {
+        match self {
+            Rope::Leaf(_) => 0,
+            Rope::Node(node) => node.depth,
+        }
+    }
+
+    /// Returns a balanced rope with the same leaves. The leaves are collected
+    /// without recursion, so this is safe on degenerate trees.
+    pub fn rebalance(&self) -> Rope {
+        let mut leaves = Vec::new();
+        let mut stack = vec![self];
+        while let Some(rope) = stack.pop() {
+            match rope {
+                Rope::Leaf(_) => leaves.push(rope.share()),
+                Rope::Node(node) => {
+                    stack.extend(node.right.as_deref());
+                    stack.extend(node.left.as_deref());
+                }
+            }
+        }
+        Rope::from_leaves(leaves)
+    }
+
+    /// Checks the depth of a rope built from untrusted input before it gets
+    /// traversed, either rebalancing it or refusing it when it is deeper than
+    /// `limit`.
+    pub fn guard_depth(&self, limit: usize, policy: DepthPolicy) -> Result<Rope, DepthError> {
+        if self.depth() <= limit {
+            return Ok(self.share());
+        }
+        let error = DepthError {
+            depth: self.depth(),
+            limit,
+        };
+        if policy == DepthPolicy::Error {
+            return Err(error);
+        }
+        let balanced = self.rebalance();
+        if balanced.depth() > limit {
+            return Err(DepthError {
+                depth: balanced.depth(),
+                ..error
+            });
+        }
+        Ok(balanced)
+    }
+}
+
+#[test]
+fn test_edits_stay_shallow() {
+    let mut rope = Rope::new("");
+    for i in 0..2000 {
+        let chunk = "x".repeat(crate::rope::MAX_LEAF_LEN);
+        rope.insert(rope.len(), &chunk);
+        assert!(rope.depth() <= MAX_DEPTH, "depth {} at {}", rope.depth(), i);
+    }
+    assert_eq!(rope.len(), 2000 * crate::rope::MAX_LEAF_LEN);
+}
+
+#[test]
+fn test_joins_stay_shallow() {
+    use std::sync::Arc;
+
+    let mut rope = Rope::new("a");
+    let mut leaves = vec![Rope::new("a")];
+    for _ in 0..1000 {
+        rope = Rope::join(Arc::new(rope), Arc::new(Rope::new("b")));
+        assert!(rope.depth() <= MAX_DEPTH);
+        leaves = vec![Rope::from_leaves(leaves), Rope::new("b")];
+    }
+    let rope = Rope::from_leaves(leaves);
+    assert!(rope.depth() <= MAX_DEPTH);
+    assert_eq!(rope.len(), 1001);
+}
+
+#[test]
+fn test_guard_depth() {
+    let mut rope = Rope::new("a");
+    for _ in 0..10 {
+        rope = Rope::join(
+            std::sync::Arc::new(rope),
+            std::sync::Arc::new(Rope::new("b")),
+        );
+    }
+    assert_eq!(rope.depth(), 10);
+
+    let err = rope.guard_depth(5, DepthPolicy::Error).unwrap_err();
+    assert_eq!(
+        err,
+        DepthError {
+            depth: 10,
+            limit: 5
+        }
+    );
+
+    let balanced = rope.guard_depth(5, DepthPolicy::Rebalance).unwrap();
+    assert_eq!(balanced.depth(), 4);
+    assert_eq!(balanced.chars().collect::<String>(), "abbbbbbbbbb");
+
+    assert!(rope.guard_depth(2, DepthPolicy::Rebalance).is_err());
+}
@@ -0,0 +1,61 @@
+use std::ops::Range;
+
+use crate::rope::Rope;
+
+/// Common editing interface shared by the text backends of this crate, so a
+/// document can switch between them without changing calling code.
+///
+/// All offsets are char offsets.
+pub trait TextBuffer {
+    fn len_chars(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len_chars() == 0
+    }
+
+    fn insert_str(&mut self, offset: usize, s: &str);
+
+    fn remove_range(&mut self, range: Range<usize>);
+
+    fn slice_to_string(&self, range: Range<usize>) -> String;
+
+    fn contents(&self) -> String {
+        self.slice_to_string(0..self.len_chars())
+    }
+}
+
+impl TextBuffer for Rope {
+    fn len_chars(&self) -> usize {
+        self.len_chars()
+    }
+
+    fn insert_str(&mut self, offset: usize, s: &str) {
+        self.insert(self.char_to_byte(offset), s);
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) {
+        if range.start < range.end {
+            self.remove(self.char_to_byte(range.start)..self.char_to_byte(range.end));
+        }
+    }
+
+    fn slice_to_string(&self, range: Range<usize>) -> String {
+        Rope::slice_to_string(self, range)
+    }
+}
+
+#[test]
+fn test_rope_text_buffer() {
+    let mut rope = Rope::new("Hello, World!");
+    rope.insert_str(6, " Cruel");
+    rope.remove_range(1..5);
+
+    assert_eq!(rope.contents(), "H, Cruel World!");
+    assert_eq!(TextBuffer::slice_to_string(&rope, 3..8), "Cruel");
+    assert_eq!(rope.len_chars(), 15);
+
+    let mut rope = Rope::new("Grüße!");
+    rope.insert_str(5, ", Welt");
+    rope.remove_range(2..4);
+    assert_eq!(rope.contents(), "Gre, Welt!");
+}
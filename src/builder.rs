@@ -0,0 +1,185 @@
+use std::io::{self, Read};
+
+use crate::rope::{Rope, MAX_LEAF_LEN};
+use crate::transform::Transform;
+
+/// Incrementally builds a balanced rope out of text pieces.
+///
+/// The builder only holds owned strings, so it can be moved to a background
+/// thread to load a file and the finished rope sent back to the UI thread.
+#[derive(Debug, Default)]
+pub struct RopeBuilder {
+    leaves: Vec<Rope>,
+    pending: String,
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl RopeBuilder {
+    pub fn new() -> RopeBuilder {
+        RopeBuilder::default()
+    }
+
+    /// Adds `transform` at the end of the chain the appended text goes
+    /// through, such as stripping a byte order mark and normalizing line
+    /// endings while a file is read.
+    pub fn with_transform<T: Transform + 'static>(mut self, transform: T) -> RopeBuilder {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Appends text, cutting it into leaves of at most `MAX_LEAF_LEN` bytes.
+    pub fn append(&mut self, s: &str) {
+        if self.transforms.is_empty() {
+            return self.push(s);
+        }
+        let text = self.transformed(s, false);
+        self.push(&text);
+    }
+
+    // runs `s` through the transforms, finishing each one at the end
+    fn transformed(&mut self, s: &str, end: bool) -> String {
+        let mut text = s.to_string();
+        for transform in self.transforms.iter_mut() {
+            let mut out = String::with_capacity(text.len());
+            transform.push(&text, &mut out);
+            if end {
+                transform.finish(&mut out);
+            }
+            text = out;
+        }
+        text
+    }
+
+    fn push(&mut self, mut s: &str) {
+        while !s.is_empty() {
+            let room = MAX_LEAF_LEN - self.pending.len();
+            if s.len() <= room {
+                self.pending.push_str(s);
+                break;
+            }
+            let mut at = room;
+            while !s.is_char_boundary(at) {
+                at -= 1;
+            }
+            let (head, tail) = s.split_at(at);
+            self.pending.push_str(head);
+            self.flush();
+            s = tail;
+        }
+    }
+
+    /// Reads UTF-8 text from `reader` until EOF and appends it.
+    pub fn read_from<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut buf = vec![0; 64 * 1024];
+        // bytes of a char split across two reads
+        let mut carry = 0;
+        loop {
+            let n = reader.read(&mut buf[carry..])?;
+            if n == 0 {
+                if carry > 0 {
+                    return Err(invalid_utf8());
+                }
+                return Ok(());
+            }
+            let len = carry + n;
+            let valid = match std::str::from_utf8(&buf[..len]) {
+                Ok(_) => len,
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err(invalid_utf8()),
+            };
+            let s = std::str::from_utf8(&buf[..valid]).expect("validated above");
+            self.append(s);
+            buf.copy_within(valid..len, 0);
+            carry = len - valid;
+        }
+    }
+
+    /// Builds the rope out of everything appended so far.
+    pub fn finish(mut self) -> Rope {
+        if !self.transforms.is_empty() {
+            let text = self.transformed("", true);
+            self.push(&text);
+        }
+        self.flush();
+        Rope::from_leaves(self.leaves)
+    }
+
+    fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            self.leaves.push(Rope::new(&self.pending));
+            self.pending.clear();
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+#[test]
+fn test_builder_chunks() {
+    let text = "abcdefgh".repeat(MAX_LEAF_LEN / 4);
+
+    let mut builder = RopeBuilder::new();
+    builder.append(&text);
+    let rope = builder.finish();
+
+    assert_eq!(rope.chunks().count(), 2);
+    assert!(rope.chunks().all(|c| c.len() <= MAX_LEAF_LEN));
+    assert_eq!(rope.chars().collect::<String>(), text);
+}
+
+#[test]
+fn test_builder_read_from() {
+    let text = "grüße ".repeat(MAX_LEAF_LEN);
+
+    let mut builder = RopeBuilder::new();
+    builder.read_from(text.as_bytes()).unwrap();
+    let rope = builder.finish();
+
+    assert_eq!(rope.chars().collect::<String>(), text);
+
+    let mut builder = RopeBuilder::new();
+    let err = builder.read_from(&b"gr\xc3"[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_builder_transforms() {
+    use crate::transform::{ExpandTabs, NormalizeLineEndings, StripBom};
+
+    let file = "\u{feff}fn main() {\r\n\tprintln!();\r\n}\r\n".repeat(100);
+    let mut builder = RopeBuilder::new()
+        .with_transform(StripBom::default())
+        .with_transform(NormalizeLineEndings::default())
+        .with_transform(ExpandTabs::new(4));
+    // reads cut the text anywhere, even between \r and \n
+    builder.read_from(file.as_bytes()).unwrap();
+    for piece in ["\r", "\n\t}"] {
+        builder.append(piece);
+    }
+    let rope = builder.finish();
+
+    let expected = "fn main() {\n    println!();\n}\n";
+    let text = rope.to_string();
+    assert!(text.starts_with(expected));
+    assert!(text.ends_with("}\n\u{feff}fn main() {\n    println!();\n}\n\n    }"));
+    assert_eq!(rope.len_lines(), 302);
+    assert!(rope.chunks().all(|c| c.len() <= MAX_LEAF_LEN));
+}
+
+#[test]
+fn test_builder_across_threads() {
+    let handle = std::thread::spawn(|| {
+        let mut builder = RopeBuilder::new();
+        builder.append("Hello, World!");
+        builder
+    });
+    let rope = handle.join().unwrap().finish();
+
+    let rope = std::thread::spawn(move || rope).join().unwrap();
+    assert_eq!(rope.chars().collect::<String>(), "Hello, World!");
+}
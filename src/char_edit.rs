@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use crate::rope::{Rope, MAX_LEAF_LEN};
+
+impl Rope {
+    /// Inserts `c` at byte offset `offset`. The char is written straight into
+    /// the leaf holding `offset` when it has room, only the path down to that
+    /// leaf is rebuilt.
+    pub fn insert_char(&mut self, offset: usize, c: char) {
+        let mut buf = [0; 4];
+        let s = c.encode_utf8(&mut buf);
+        let patched = self.patch_leaf(offset, true, &mut |text, at| {
+            if text.len() + s.len() > MAX_LEAF_LEN {
+                return None;
+            }
+            let mut patched = String::with_capacity(text.len() + s.len());
+            patched.push_str(&text[..at]);
+            patched.push_str(s);
+            patched.push_str(&text[at..]);
+            Some(patched)
+        });
+        match patched {
+            Some(rope) => *self = rope,
+            None => self.insert(offset, s),
+        }
+    }
+
+    /// Removes the char starting at byte offset `offset` and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not the start of a char of the rope.
+    pub fn remove_char(&mut self, offset: usize) -> char {
+        assert!(offset < self.len(), "offset {} out of bounds", offset);
+        let mut removed = None;
+        let patched = self.patch_leaf(offset, false, &mut |text, at| {
+            let c = text[at..].chars().next()?;
+            removed = Some(c);
+            Some(text[..at].to_string() + &text[at + c.len_utf8()..])
+        });
+        *self = patched.expect("offset within the rope");
+        removed.expect("removed a char")
+    }
+
+    /// Appends `c` at the end. The char goes into the last leaf while it has
+    /// room, so a rope built one char at a time still has full leaves.
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Removes the last char and returns it, or none if the rope is empty.
+    pub fn pop(&mut self) -> Option<char> {
+        let c = self.last_char()?;
+        Some(self.remove_char(self.len() - c.len_utf8()))
+    }
+
+    fn last_char(&self) -> Option<char> {
+        match self {
+            Rope::Leaf(leaf) => leaf.as_str().chars().next_back(),
+            Rope::Node(node) => node.right.as_ref()?.last_char(),
+        }
+    }
+
+    // the rope with the leaf holding `offset` replaced by what `patch` makes
+    // of its text and the offset in it, or none if `patch` gives up. With
+    // `at_end` an offset between two leaves goes to the end of the first one.
+    fn patch_leaf(
+        &self,
+        offset: usize,
+        at_end: bool,
+        patch: &mut dyn FnMut(&str, usize) -> Option<String>,
+    ) -> Option<Rope> {
+        match self {
+            Rope::Leaf(leaf) => patch(leaf.as_str(), offset).map(|text| Rope::new(&text)),
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let right = node.right.as_ref().expect("right child cannot be empty");
+                let w = node.weight.get();
+                if offset < w || (at_end && offset == w) {
+                    let left = left.patch_leaf(offset, at_end, patch)?;
+                    return Some(Rope::join(Arc::new(left), Arc::clone(right)));
+                }
+                let right = right.patch_leaf(offset - w, at_end, patch)?;
+                Some(Rope::join(Arc::clone(left), Arc::new(right)))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_insert_char() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Hllo, "), Rope::new("Wrld")]);
+    rope.insert_char(1, 'e');
+    rope.insert_char(8, 'ö');
+    rope.insert_char(rope.len(), '!');
+    assert_eq!(rope.chars().collect::<String>(), "Hello, Wörld!");
+    assert_eq!(rope.chunks().collect::<Vec<_>>(), vec!["Hello, ", "Wörld!"]);
+
+    let mut rope = Rope::new(&"x".repeat(MAX_LEAF_LEN));
+    rope.insert_char(3, 'y');
+    assert_eq!(rope.len(), MAX_LEAF_LEN + 1);
+    assert_eq!(rope.chars_at(3).next(), Some('y'));
+}
+
+#[test]
+fn test_remove_char() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new("!")]);
+    assert_eq!(rope.remove_char(2), 'ü');
+    assert_eq!(rope.remove_char(5), '!');
+    assert_eq!(rope.chars().collect::<String>(), "Grße");
+    assert_eq!(rope.len_chars(), 4);
+    assert!(rope.chunks().all(|chunk| !chunk.is_empty()));
+}
+
+#[test]
+fn test_push_and_pop() {
+    let mut rope = Rope::empty();
+    for c in "grüße, ".chars().cycle().take(3 * MAX_LEAF_LEN) {
+        rope.push(c);
+    }
+    assert_eq!(rope.len_chars(), 3 * MAX_LEAF_LEN);
+    // full leaves rather than one per char
+    assert!(rope.chunks().count() <= 5);
+
+    // 3 * MAX_LEAF_LEN chars stop right after a ','
+    assert_eq!(rope.pop(), Some(','));
+    assert_eq!(rope.pop(), Some('e'));
+    assert_eq!(rope.pop(), Some('ß'));
+    assert_eq!(rope.pop(), Some('ü'));
+    assert_eq!(rope.len_chars(), 3 * MAX_LEAF_LEN - 4);
+
+    let mut rope = Rope::new("ö");
+    assert_eq!(rope.pop(), Some('ö'));
+    assert_eq!(rope.pop(), None);
+    assert!(rope.is_empty());
+}
@@ -0,0 +1,89 @@
+use crate::compare::common_len;
+use crate::rope::Rope;
+use crate::slice::SliceChunks;
+use crate::version::Version;
+
+/// A saved position to read chunks from, for incremental lexers: save one
+/// at each restart point, and after an edit resume from the last one the
+/// edit left intact instead of lexing from the start.
+///
+/// A cursor remembers the version of the rope it was made on. It stays
+/// valid as long as later edits don't change any text before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkCursor {
+    version: Version,
+    offset: usize,
+}
+
+impl Rope {
+    /// A cursor at byte offset `offset`, clamped to the rope.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` falls inside a char.
+    pub fn chunk_cursor(&self, offset: usize) -> ChunkCursor {
+        let offset = offset.min(self.len());
+        assert!(
+            self.is_char_boundary(offset),
+            "byte offset {} is not a char boundary",
+            offset
+        );
+        ChunkCursor {
+            version: self.version(),
+            offset,
+        }
+    }
+}
+
+impl ChunkCursor {
+    /// Byte offset of the cursor.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The chunks of `rope` from the cursor on, if `rope` is the version
+    /// the cursor was made on or one edited only at or after the cursor.
+    /// Otherwise the byte offset of the first change, so the caller can go
+    /// back to a cursor before it.
+    ///
+    /// Finding the first change skips the subtrees both versions share, so
+    /// this is cheap for the small edits of an editor.
+    pub fn resume<'a>(&self, rope: &'a Rope) -> Result<SliceChunks<'a>, usize> {
+        if !rope.ptr_eq_subtree(&self.version.0) {
+            let unchanged = common_len(&self.version.0, rope, false);
+            if unchanged < self.offset {
+                return Err(unchanged);
+            }
+        }
+        Ok(rope.byte_slice(self.offset..).chunks())
+    }
+}
+
+#[test]
+fn test_chunk_cursor() {
+    let text = "let x = 1;\n".repeat(200);
+    let mut builder = crate::RopeBuilder::new();
+    builder.append(&text);
+    let mut rope = builder.finish();
+
+    let early = rope.chunk_cursor(110);
+    let late = rope.chunk_cursor(1100);
+    assert_eq!(
+        late.resume(&rope).unwrap().collect::<String>(),
+        text[1100..]
+    );
+
+    // an edit between the cursors leaves the early one valid
+    rope.insert(550, "// note\n");
+    let resumed: String = early.resume(&rope).unwrap().collect();
+    assert!(resumed.starts_with("let x = 1;\n"));
+    assert_eq!(resumed.len(), rope.len() - 110);
+    assert_eq!(late.resume(&rope).err(), Some(550));
+
+    // an edit right at the cursor does too
+    let cursor = rope.chunk_cursor(11);
+    rope.insert(11, "x");
+    let resumed: String = cursor.resume(&rope).unwrap().collect();
+    assert!(resumed.starts_with("xlet"));
+    assert_eq!(cursor.offset(), 11);
+}
@@ -0,0 +1,364 @@
+use std::ops::{Bound, Range, RangeBounds};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, JoinHandle};
+
+use crate::lz;
+use crate::rope::Rope;
+
+// the unit text is compressed in; blocks hold between one and two of these
+// except at the end of the text or after a deletion
+const BLOCK_LEN: usize = 64 * 1024;
+
+/// A rope for huge, mostly unviewed documents such as logs, which keeps the
+/// parts nobody works on compressed. The text is cut into blocks of about
+/// 64 KiB. Every `cold_after` edits, the blocks no edit or read reached in
+/// the last `cold_after` edits are compressed; the next edit or read
+/// reaching one decompresses it again.
+///
+/// Reads take `&mut self`, as they may have to decompress blocks;
+/// `hint_hot_range` gets that done ahead of time. Offsets are byte offsets.
+#[derive(Debug)]
+pub struct ColdRope {
+    blocks: Vec<Block>,
+    edits: usize,
+    cold_after: usize,
+}
+
+#[derive(Debug)]
+struct Block {
+    len: usize,
+    // edit count when the block was last reached
+    touched: usize,
+    text: Text,
+}
+
+#[derive(Debug)]
+enum Text {
+    Hot(Rope),
+    Cold(Arc<Frozen>),
+}
+
+#[derive(Debug)]
+struct Frozen {
+    packed: Arc<[u8]>,
+    // filled ahead of the first read by `hint_hot_range`
+    thawed: OnceLock<Rope>,
+}
+
+impl Frozen {
+    fn new(packed: Arc<[u8]>) -> Arc<Frozen> {
+        Arc::new(Frozen {
+            packed,
+            thawed: OnceLock::new(),
+        })
+    }
+
+    fn thaw(&self) -> &Rope {
+        self.thawed.get_or_init(|| {
+            let bytes = lz::decompress(&self.packed).expect("compressed block is intact");
+            Rope::chunked(&String::from_utf8(bytes).expect("compressed block is UTF-8"))
+        })
+    }
+}
+
+impl ColdRope {
+    /// Holds `rope`, compressing the blocks left alone for `cold_after`
+    /// edits. Everything starts out uncompressed.
+    pub fn new(rope: Rope, cold_after: usize) -> ColdRope {
+        let mut cold = ColdRope {
+            blocks: Vec::new(),
+            edits: 0,
+            cold_after: cold_after.max(1),
+        };
+        cold.blocks = cold.cut(rope);
+        cold
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.iter().map(|block| block.len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of blocks currently compressed.
+    pub fn cold_blocks(&self) -> usize {
+        let cold = |block: &&Block| matches!(block.text, Text::Cold(_));
+        self.blocks.iter().filter(cold).count()
+    }
+
+    /// Bytes the text takes up as stored: compressed blocks count their
+    /// compressed size, plus their text once decompressed ahead of time.
+    pub fn stored_len(&self) -> usize {
+        let stored = |block: &Block| match &block.text {
+            Text::Hot(_) => block.len,
+            Text::Cold(frozen) => frozen.packed.len() + frozen.thawed.get().map_or(0, Rope::len),
+        };
+        self.blocks.iter().map(stored).sum()
+    }
+
+    /// Replaces the bytes in `range` with `s`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed, ends past the end of the text or
+    /// does not fall on char boundaries.
+    pub fn edit<R: RangeBounds<usize>>(&mut self, range: R, s: &str) {
+        let range = self.byte_range(range);
+        let (blocks, offset) = self.span(&range);
+        let mut rope = self.warm_span(blocks.clone());
+        rope.replace_range(range.start - offset..range.end - offset, s);
+        let replacement = self.cut(rope);
+        self.blocks.splice(blocks, replacement);
+        if self.blocks.is_empty() {
+            self.blocks = self.cut(Rope::empty());
+        }
+
+        self.edits += 1;
+        if self.edits.is_multiple_of(self.cold_after) {
+            self.freeze();
+        }
+    }
+
+    pub fn insert(&mut self, offset: usize, s: &str) {
+        self.edit(offset..offset, s)
+    }
+
+    pub fn remove<R: RangeBounds<usize>>(&mut self, range: R) {
+        self.edit(range, "")
+    }
+
+    /// The text in `range` as a rope of its own, sharing leaves with the
+    /// blocks it covers. Those are decompressed if they have to be.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the same ranges as `edit`.
+    pub fn slice<R: RangeBounds<usize>>(&mut self, range: R) -> Rope {
+        let range = self.byte_range(range);
+        let (blocks, offset) = self.span(&range);
+        let rope = self.warm_span(blocks);
+        rope.byte_slice(range.start - offset..range.end - offset)
+            .to_rope()
+    }
+
+    /// Starts decompressing the blocks holding `range` and the ones next to
+    /// them on a background thread, so the reads that follow, as when
+    /// scrolling through a viewport, find them ready. A read reaching a
+    /// block still being decompressed waits for it. Blocks hinted at count as
+    /// reached, and if no read follows they are dropped back to their
+    /// compressed form along with the others.
+    ///
+    /// Returns the thread, if there was anything to decompress.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the same ranges as `edit`.
+    pub fn hint_hot_range<R: RangeBounds<usize>>(&mut self, range: R) -> Option<JoinHandle<()>> {
+        let range = self.byte_range(range);
+        let (blocks, _) = self.span(&range);
+        let near = blocks.start.saturating_sub(1)..(blocks.end + 1).min(self.blocks.len());
+
+        let mut frozen = Vec::new();
+        for block in &mut self.blocks[near] {
+            block.touched = self.edits;
+            if let Text::Cold(cold) = &block.text {
+                if cold.thawed.get().is_none() {
+                    frozen.push(Arc::clone(cold));
+                }
+            }
+        }
+        if frozen.is_empty() {
+            return None;
+        }
+        Some(thread::spawn(move || {
+            for block in frozen {
+                block.thaw();
+            }
+        }))
+    }
+
+    /// The whole text as a plain rope, decompressing everything.
+    pub fn into_rope(mut self) -> Rope {
+        self.slice(..)
+    }
+
+    // the blocks holding `range`, and the byte offset of the first one
+    fn span(&self, range: &Range<usize>) -> (Range<usize>, usize) {
+        let last = self.blocks.len() - 1;
+        let (mut first, mut offset) = (0, 0);
+        while first < last && offset + self.blocks[first].len <= range.start {
+            offset += self.blocks[first].len;
+            first += 1;
+        }
+        let (mut end, mut block_end) = (first, offset + self.blocks[first].len);
+        while end < last && block_end < range.end {
+            end += 1;
+            block_end += self.blocks[end].len;
+        }
+        (first..end + 1, offset)
+    }
+
+    // the text of `blocks` as one rope, decompressing the cold ones
+    fn warm_span(&mut self, blocks: Range<usize>) -> Rope {
+        let edits = self.edits;
+        let mut rope = Rope::empty();
+        for block in &mut self.blocks[blocks] {
+            if let Text::Cold(frozen) = &block.text {
+                block.text = Text::Hot(frozen.thaw().share());
+            }
+            if let Text::Hot(text) = &block.text {
+                rope = Rope::concat(rope, text.share());
+            }
+            block.touched = edits;
+        }
+        rope
+    }
+
+    // compresses the blocks left alone for `cold_after` edits, and drops the
+    // text decompressed ahead of time but never read
+    fn freeze(&mut self) {
+        for block in &mut self.blocks {
+            if block.touched + self.cold_after > self.edits {
+                continue;
+            }
+            let text = match &block.text {
+                Text::Hot(text) => text,
+                Text::Cold(frozen) => {
+                    if frozen.thawed.get().is_some() {
+                        block.text = Text::Cold(Frozen::new(Arc::clone(&frozen.packed)));
+                    }
+                    continue;
+                }
+            };
+            let packed = lz::compress(text.to_string().as_bytes());
+            match packed.len() < block.len {
+                true => block.text = Text::Cold(Frozen::new(packed.into())),
+                // not worth it, try again later
+                false => block.touched = self.edits,
+            }
+        }
+    }
+
+    // `rope` cut into blocks
+    fn cut(&self, mut rope: Rope) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        while rope.len() >= 2 * BLOCK_LEN {
+            let mut at = BLOCK_LEN;
+            while !rope.is_char_boundary(at) {
+                at += 1;
+            }
+            let (head, rest) = rope.split(at);
+            blocks.push(self.block(head));
+            rope = rest;
+        }
+        if !rope.is_empty() || blocks.is_empty() {
+            blocks.push(self.block(rope));
+        }
+        blocks
+    }
+
+    fn block(&self, rope: Rope) -> Block {
+        Block {
+            len: rope.len(),
+            touched: self.edits,
+            text: Text::Hot(rope),
+        }
+    }
+
+    fn byte_range<R: RangeBounds<usize>>(&self, range: R) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.saturating_add(1),
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for length {}",
+            start,
+            end,
+            self.len()
+        );
+        start..end
+    }
+}
+
+#[test]
+fn test_cold_rope() {
+    let line = "2024-01-01 12:00:00 INFO request served in 3ms\n";
+    let mut text = line.repeat(20_000);
+    let mut rope = crate::RopeBuilder::new();
+    rope.append(&text);
+    let mut cold = ColdRope::new(rope.finish(), 3);
+    assert_eq!(cold.len(), text.len());
+    assert_eq!(cold.cold_blocks(), 0);
+
+    // a few edits at the top leave the rest to be compressed
+    for i in 0..3 {
+        cold.insert(i, "#");
+        text.insert(i, '#');
+    }
+    let frozen = cold.cold_blocks();
+    assert!(frozen > 5);
+    assert!(cold.stored_len() < text.len() / 10);
+
+    // reading or editing in the middle brings the text back
+    let mid = text.len() / 2;
+    assert_eq!(cold.slice(mid..mid + 100).to_string(), text[mid..mid + 100]);
+    assert_eq!(cold.cold_blocks(), frozen - 1);
+    cold.remove(mid - 70_000..mid + 70_000);
+    text.replace_range(mid - 70_000..mid + 70_000, "");
+    cold.insert(cold.len(), "ünïcode\n");
+    text.push_str("ünïcode\n");
+    assert_eq!(cold.len(), text.len());
+    assert_eq!(cold.into_rope().to_string(), text);
+}
+
+#[test]
+fn test_cold_rope_small() {
+    let mut cold = ColdRope::new(Rope::empty(), 1);
+    assert!(cold.is_empty());
+    cold.insert(0, "héllo");
+    cold.remove(..);
+    cold.insert(0, "world");
+    assert_eq!(cold.slice(1..=2).to_string(), "or");
+    assert_eq!(cold.into_rope().to_string(), "world");
+}
+
+#[test]
+fn test_hint_hot_range() {
+    let text = "GET /index.html 200\n".repeat(40_000);
+    let mut cold = ColdRope::new(Rope::chunked(&text), 2);
+    cold.insert(0, "#");
+    cold.insert(0, "#");
+    let frozen = cold.cold_blocks();
+    let stored = cold.stored_len();
+    assert!(frozen > 8);
+
+    // the viewport's blocks and their neighbours get decompressed
+    let mid = text.len() / 2;
+    cold.hint_hot_range(mid..mid + 100).unwrap().join().unwrap();
+    let thawed = cold.stored_len() - stored;
+    assert!((2 * BLOCK_LEN..4 * BLOCK_LEN).contains(&thawed));
+    assert!(cold.hint_hot_range(mid..mid + 100).is_none());
+    assert_eq!(cold.cold_blocks(), frozen);
+    assert_eq!(
+        cold.slice(mid..mid + 100).to_string(),
+        text[mid - 2..mid + 98]
+    );
+    assert_eq!(cold.cold_blocks(), frozen - 1);
+
+    // left unread, the decompressed text goes away again
+    cold.insert(0, "#");
+    cold.insert(0, "#");
+    cold.insert(0, "#");
+    cold.insert(0, "#");
+    assert_eq!(cold.cold_blocks(), frozen);
+    assert_eq!(cold.stored_len(), stored + 4);
+}
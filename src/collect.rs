@@ -0,0 +1,137 @@
+use std::iter::FromIterator;
+
+use crate::builder::RopeBuilder;
+use crate::rope::Rope;
+#[cfg(test)]
+use crate::rope::MAX_LEAF_LEN;
+
+impl Extend<Rope> for Rope {
+    /// Appends all the ropes at the end. The tree over the appended ropes is
+    /// built once, balanced, and then appended like `append` does, instead
+    /// of joining them one by one.
+    fn extend<I: IntoIterator<Item = Rope>>(&mut self, iter: I) {
+        self.append(iter.into_iter().collect());
+    }
+}
+
+impl Extend<char> for Rope {
+    /// Appends the chars, packed into full leaves rather than one per char.
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        self.append(iter.into_iter().collect());
+    }
+}
+
+impl<'a> Extend<&'a str> for Rope {
+    /// Appends the pieces, packing small ones together into full leaves.
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        self.append(iter.into_iter().collect());
+    }
+}
+
+impl Extend<String> for Rope {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, iter: I) {
+        self.append(iter.into_iter().collect());
+    }
+}
+
+impl FromIterator<Rope> for Rope {
+    /// Concatenates a stream of ropes into one balanced rope.
+    fn from_iter<I: IntoIterator<Item = Rope>>(iter: I) -> Rope {
+        Rope::from_leaves(iter.into_iter().filter(|rope| !rope.is_empty()).collect())
+    }
+}
+
+impl FromIterator<char> for Rope {
+    /// Builds a balanced rope with full leaves out of a stream of chars.
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Rope {
+        let mut builder = RopeBuilder::new();
+        for c in iter {
+            builder.append(c.encode_utf8(&mut [0; 4]));
+        }
+        builder.finish()
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Rope {
+    /// Builds a balanced rope out of a stream of string pieces, packing
+    /// small pieces together into full leaves.
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Rope {
+        let mut builder = RopeBuilder::new();
+        iter.into_iter().for_each(|s| builder.append(s));
+        builder.finish()
+    }
+}
+
+impl FromIterator<String> for Rope {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Rope {
+        let mut builder = RopeBuilder::new();
+        iter.into_iter().for_each(|s| builder.append(&s));
+        builder.finish()
+    }
+}
+
+#[test]
+fn test_extend_ropes() {
+    let mut rope = Rope::new("Hello");
+    rope.extend(vec![
+        Rope::new(","),
+        Rope::new(""),
+        Rope::new(" World"),
+        Rope::new("!"),
+    ]);
+
+    assert_eq!(rope.chars().collect::<String>(), "Hello, World!");
+    assert_eq!(rope.chunks().count(), 4);
+}
+
+#[test]
+fn test_extend_ropes_stays_shallow() {
+    let mut rope = Rope::new("start");
+    for _ in 0..100_000 {
+        rope.extend(Some(Rope::new("x")));
+    }
+    assert_eq!(rope.len(), 100_005);
+    assert!(rope.depth() <= crate::MAX_DEPTH, "depth {}", rope.depth());
+}
+
+#[test]
+fn test_collect_ropes() {
+    let rope: Rope = vec!["a\n", "b\n", "c"].into_iter().map(Rope::new).collect();
+
+    assert_eq!(rope.chars().collect::<String>(), "a\nb\nc");
+    assert_eq!(rope.len_lines(), 3);
+
+    let rope: Rope = Vec::<Rope>::new().into_iter().collect();
+    assert_eq!(rope.len(), 0);
+}
+
+#[test]
+fn test_collect_chars_and_strings() {
+    let text = "grüße\n".repeat(MAX_LEAF_LEN / 2);
+
+    let rope: Rope = text.chars().collect();
+    assert_eq!(rope.to_string(), text);
+    assert!(rope.chunks().count() <= text.len() / MAX_LEAF_LEN + 1);
+
+    let rope: Rope = text.split_inclusive('\n').collect();
+    assert_eq!(rope.to_string(), text);
+    assert!(rope.chunks().count() <= text.len() / MAX_LEAF_LEN + 1);
+
+    let rope: Rope = (0..3).map(|i| i.to_string()).collect();
+    assert_eq!(rope.to_string(), "012");
+    assert!(std::iter::empty::<char>().collect::<Rope>().is_empty());
+}
+
+#[test]
+fn test_extend_chars_and_strings() {
+    let mut rope = Rope::new("lines:\n");
+    rope.extend((0..1000).map(|_| "line\n"));
+    assert_eq!(rope.len_lines(), 1002);
+    assert!(rope.chunks().count() <= 6);
+
+    let mut rope = Rope::new("abc");
+    rope.extend("déf".chars());
+    rope.extend(vec![String::from("!")]);
+    assert_eq!(rope.to_string(), "abcdéf!");
+    assert_eq!(rope.chunks().count(), 1);
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use crate::rope::{Leaf, Node, Rope, MAX_LEAF_LEN};
+
+/// Fraction of its buffer below which a leaf left over by an edit copies its
+/// text out, so that a few small leaves don't keep a large buffer alive.
+pub const MIN_VIEWED: f64 = 0.25;
+
+impl Leaf {
+    // whether the leaf views less than `min_viewed` of a buffer large enough
+    // to be worth releasing
+    fn is_sparse(&self, min_viewed: f64) -> bool {
+        let len = self.buf.len();
+        len > MAX_LEAF_LEN && ((self.end - self.start) as f64) < len as f64 * min_viewed
+    }
+
+    pub(crate) fn compacted(self, min_viewed: f64) -> Leaf {
+        if !self.is_sparse(min_viewed) {
+            return self;
+        }
+        let buf = Arc::new(self.as_str().to_string());
+        Leaf {
+            start: 0,
+            end: buf.len(),
+            buf,
+            ..self
+        }
+    }
+}
+
+impl Rope {
+    /// Returns a rope where every leaf viewing less than `min_viewed` of its
+    /// buffer holds a copy of its text instead, trading the copies for
+    /// releasing the buffers once nothing else uses them. Subtrees with
+    /// nothing to copy are shared.
+    pub fn compact(&self, min_viewed: f64) -> Rope {
+        if !self.has_sparse_leaves(min_viewed) {
+            return self.share();
+        }
+        match self {
+            Rope::Leaf(leaf) => Rope::Leaf(
+                Leaf {
+                    buf: Arc::clone(&leaf.buf),
+                    ..*leaf
+                }
+                .compacted(min_viewed),
+            ),
+            Rope::Node(node) => {
+                let compact = |child: &Option<Arc<Rope>>| {
+                    child
+                        .as_ref()
+                        .map(|c| match c.has_sparse_leaves(min_viewed) {
+                            true => Arc::new(c.compact(min_viewed)),
+                            false => Arc::clone(c),
+                        })
+                };
+                Rope::Node(Node {
+                    left: compact(&node.left),
+                    right: compact(&node.right),
+                    ..*node
+                })
+            }
+        }
+    }
+
+    /// Returns a copy of the rope sharing nothing with it, nor within
+    /// itself: every leaf gets a buffer of its own. For handing text over to
+    /// code that must not hold on to anything else. Subtrees repeated within
+    /// the rope, as made by `repeat`, are copied out once per occurrence.
+    pub fn deep_clone_owned(&self) -> Rope {
+        match self {
+            Rope::Leaf(leaf) => Rope::new(leaf.as_str()),
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let right = node.right.as_ref().expect("right child cannot be empty");
+                Rope::join(
+                    Arc::new(left.deep_clone_owned()),
+                    Arc::new(right.deep_clone_owned()),
+                )
+            }
+        }
+    }
+
+    fn has_sparse_leaves(&self, min_viewed: f64) -> bool {
+        let mut stack = vec![self];
+        while let Some(rope) = stack.pop() {
+            match rope {
+                Rope::Leaf(leaf) if leaf.is_sparse(min_viewed) => return true,
+                Rope::Leaf(_) => {}
+                Rope::Node(node) => {
+                    stack.extend(node.left.as_deref());
+                    stack.extend(node.right.as_deref());
+                }
+            }
+        }
+        false
+    }
+}
+
+// largest buffer any leaf of `rope` keeps alive
+#[cfg(test)]
+fn largest_buffer(rope: &Rope) -> usize {
+    match rope {
+        Rope::Leaf(leaf) => leaf.buf.len(),
+        Rope::Node(node) => {
+            let left = node.left.as_deref().map_or(0, largest_buffer);
+            left.max(node.right.as_deref().map_or(0, largest_buffer))
+        }
+    }
+}
+
+#[test]
+fn test_edits_release_sparse_buffers() {
+    let rope = Rope::new(&"x".repeat(4 * MAX_LEAF_LEN));
+
+    let head = rope.slice(0..3 * MAX_LEAF_LEN).to_rope();
+    assert_eq!(largest_buffer(&head), 4 * MAX_LEAF_LEN);
+    let tail = rope.slice(3 * MAX_LEAF_LEN + 1..4 * MAX_LEAF_LEN).to_rope();
+    assert_eq!(largest_buffer(&tail), MAX_LEAF_LEN - 1);
+
+    let mut edited = rope.share();
+    edited.delete(10, 4 * MAX_LEAF_LEN - 10);
+    assert_eq!(edited.chars().collect::<String>(), "x".repeat(19));
+    assert!(largest_buffer(&edited) < MAX_LEAF_LEN);
+}
+
+#[test]
+fn test_compact() {
+    let text = "line\n".repeat(2 * MAX_LEAF_LEN);
+    let index = Rope::chunked(&text).line_index();
+    let rope = Rope::from_line_index(text.clone(), &index).unwrap();
+    assert_eq!(largest_buffer(&rope), text.len());
+
+    let compacted = rope.compact(MIN_VIEWED);
+    assert_eq!(compacted.chars().collect::<String>(), text);
+    assert!(largest_buffer(&compacted) <= MAX_LEAF_LEN);
+    assert_eq!(compacted.len_lines(), rope.len_lines());
+
+    let small = Rope::new("small");
+    assert!(small.compact(1.0).chunks().eq(small.chunks()));
+}
+
+#[test]
+fn test_deep_clone_owned() {
+    let rope = Rope::new("ab").repeat(4);
+    let copy = rope.deep_clone_owned();
+
+    assert_eq!(copy.to_string(), "abababab");
+    assert_eq!(copy.depth(), rope.depth());
+    let mut bufs: Vec<_> = copy.chunks().map(str::as_ptr).collect();
+    bufs.dedup();
+    assert_eq!(bufs.len(), 4);
+    assert!(!copy
+        .chunks()
+        .any(|c| c.as_ptr() == rope.chunks().next().unwrap().as_ptr()));
+}
@@ -0,0 +1,693 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::rope::{Chars, Rope};
+
+/// A region that differs between a snapshot and a later version of a rope,
+/// as char ranges in each of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedRange {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// A run of char offsets over which two ropes either agree or differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub range: Range<usize>,
+    pub equal: bool,
+}
+
+impl Rope {
+    /// Walks both ropes side by side, yielding alternating runs of equal and
+    /// different content. Once the shorter rope ends the remainder of the
+    /// longer one is reported as a different run.
+    pub fn zip_compare<'a>(&'a self, other: &'a Rope) -> ZipCompare<'a> {
+        // the common prefix is found skipping shared subtrees, only the rest
+        // is walked char by char
+        let start = self
+            .first_difference(other)
+            .unwrap_or_else(|| self.len_chars());
+        ZipCompare {
+            left: self.chars_at(start),
+            right: other.chars_at(start),
+            pos: start,
+            prefix: start,
+            pending: None,
+        }
+    }
+
+    /// Char offset of the first position where the two ropes differ, if any.
+    /// Subtrees shared by both ropes at the same offset are skipped without
+    /// looking at their content.
+    pub fn first_difference(&self, other: &Rope) -> Option<usize> {
+        let common = common_len(self, other, false);
+        if common == self.len() && common == other.len() {
+            return None;
+        }
+        Some(self.byte_to_char(common))
+    }
+
+    /// The region that changed between `snapshot` and self. Both ropes are
+    /// walked from each end skipping shared subtrees, so an edit to a large
+    /// document is found in O(log n) per untouched subtree.
+    pub fn changes_since(&self, snapshot: &Rope) -> impl Iterator<Item = ChangedRange> {
+        let prefix = common_len(snapshot, self, false);
+        let shortest = snapshot.len().min(self.len());
+        let suffix = common_len(snapshot, self, true).min(shortest - prefix);
+
+        let old = snapshot.byte_to_char(prefix)..snapshot.byte_to_char(snapshot.len() - suffix);
+        let new = self.byte_to_char(prefix)..self.byte_to_char(self.len() - suffix);
+        let changed = prefix < snapshot.len().max(self.len()) - suffix;
+        changed.then_some(ChangedRange { old, new }).into_iter()
+    }
+
+    /// Whether both ropes are the very same tree, in which case they're equal
+    /// without looking at the content. Cheap snapshot comparison: edits share
+    /// all untouched subtrees with the rope they were made from.
+    pub fn ptr_eq_subtree(&self, other: &Rope) -> bool {
+        if std::ptr::eq(self, other) {
+            return true;
+        }
+        match (self, other) {
+            (Rope::Leaf(a), Rope::Leaf(b)) => {
+                Arc::ptr_eq(&a.buf, &b.buf) && a.start == b.start && a.end == b.end
+            }
+            (Rope::Node(a), Rope::Node(b)) => {
+                ptr_eq_child(&a.left, &b.left) && ptr_eq_child(&a.right, &b.right)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Rope {
+    /// Whether `a` and `b` are the same tree, like `Arc::ptr_eq`. Two ropes
+    /// with the same root hold the same text, so diffing and undo layers can
+    /// skip comparing them.
+    pub fn same_root(a: &Rope, b: &Rope) -> bool {
+        a.ptr_eq_subtree(b)
+    }
+
+    /// Bytes of self held in subtrees that `other` shares, wherever they sit
+    /// in either tree. Leaves count as shared when they are views of the
+    /// same part of the same buffer. Walks all of `other` once, and self
+    /// down to the shared subtrees.
+    pub fn shared_len(&self, other: &Rope) -> usize {
+        if Rope::same_root(self, other) {
+            return self.len();
+        }
+        let mut subtrees = Subtrees::default();
+        let mut stack = vec![other];
+        while let Some(rope) = stack.pop() {
+            match rope {
+                Rope::Node(node) => {
+                    for child in node.left.iter().chain(node.right.iter()) {
+                        subtrees.nodes.insert(Arc::as_ptr(child));
+                        stack.push(child);
+                    }
+                }
+                Rope::Leaf(leaf) => {
+                    subtrees
+                        .leaves
+                        .insert((Arc::as_ptr(&leaf.buf), leaf.start, leaf.end));
+                }
+            }
+        }
+        subtrees.shared_len(self)
+    }
+
+    /// Whether any subtree of self is shared with `other`.
+    pub fn shares_subtree(&self, other: &Rope) -> bool {
+        self.shared_len(other) > 0
+    }
+}
+
+// the subtrees of a rope, by address
+#[derive(Default)]
+struct Subtrees {
+    nodes: HashSet<*const Rope>,
+    leaves: HashSet<(*const String, usize, usize)>,
+}
+
+impl Subtrees {
+    fn shared_len(&self, rope: &Rope) -> usize {
+        match rope {
+            Rope::Node(node) => node
+                .left
+                .iter()
+                .chain(node.right.iter())
+                .map(|child| match self.nodes.contains(&Arc::as_ptr(child)) {
+                    true => child.len(),
+                    false => self.shared_len(child),
+                })
+                .sum(),
+            Rope::Leaf(leaf) => {
+                let key = (Arc::as_ptr(&leaf.buf), leaf.start, leaf.end);
+                match self.leaves.contains(&key) {
+                    true => rope.len(),
+                    false => 0,
+                }
+            }
+        }
+    }
+}
+
+fn ptr_eq_child(a: &Option<Arc<Rope>>, b: &Option<Arc<Rope>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// replaces the node on top of the stack with its children, ordered so the
+// child to visit first ends up on top
+fn descend(stack: &mut Vec<&Rope>, reverse: bool) {
+    if let Some(Rope::Node(node)) = stack.pop() {
+        let (first, second) = match reverse {
+            false => (node.left.as_ref(), node.right.as_ref()),
+            true => (node.right.as_ref(), node.left.as_ref()),
+        };
+        if let Some(second) = second {
+            stack.push(second);
+        }
+        if let Some(first) = first {
+            stack.push(first);
+        }
+    }
+}
+
+// length in bytes of the common prefix (or suffix, if `reverse`) of two ropes,
+// skipping subtrees that both share at the same position
+pub(crate) fn common_len(a: &Rope, b: &Rope, reverse: bool) -> usize {
+    let (mut left, mut right) = (vec![a], vec![b]);
+    // bytes already consumed from the leaf on top of each stack
+    let (mut lskip, mut rskip) = (0, 0);
+    let mut common = 0;
+
+    loop {
+        let (l, r) = match (left.last(), right.last()) {
+            (Some(l), Some(r)) => (*l, *r),
+            (Some(l), None) | (None, Some(l)) if l.is_empty() => {
+                left.pop();
+                right.pop();
+                continue;
+            }
+            _ => return common,
+        };
+        if lskip == 0 && rskip == 0 && l.ptr_eq_subtree(r) {
+            left.pop();
+            right.pop();
+            common += l.len();
+            continue;
+        }
+
+        // descend into the larger side first, so shared subtrees of
+        // different depths line up
+        let (descend_left, descend_right) = match (l, r) {
+            (Rope::Node(_), Rope::Node(_)) => (l.len() >= r.len(), r.len() >= l.len()),
+            (Rope::Node(_), _) => (true, false),
+            (_, Rope::Node(_)) => (false, true),
+            _ => (false, false),
+        };
+        if descend_left || descend_right {
+            if descend_left {
+                descend(&mut left, reverse);
+            }
+            if descend_right {
+                descend(&mut right, reverse);
+            }
+            continue;
+        }
+
+        let (ls, rs) = match (l, r) {
+            (Rope::Leaf(l), Rope::Leaf(r)) => (l.as_str(), r.as_str()),
+            _ => unreachable!("both sides are leaves"),
+        };
+        let (ls, rs) = match reverse {
+            false => (&ls.as_bytes()[lskip..], &rs.as_bytes()[rskip..]),
+            true => (
+                &ls.as_bytes()[..ls.len() - lskip],
+                &rs.as_bytes()[..rs.len() - rskip],
+            ),
+        };
+        let n = ls.len().min(rs.len());
+        let same = |i: usize| match reverse {
+            false => ls[i] == rs[i],
+            true => ls[ls.len() - 1 - i] == rs[rs.len() - 1 - i],
+        };
+        if let Some(mut i) = (0..n).find(|&i| !same(i)) {
+            // back off to the start of the char holding the mismatch
+            let boundary = |i: usize| match reverse {
+                false => i == ls.len() || (ls[i] as i8) >= -0x40,
+                true => i == 0 || (ls[ls.len() - i] as i8) >= -0x40,
+            };
+            while !boundary(i) {
+                i -= 1;
+            }
+            return common + i;
+        }
+        common += n;
+        lskip += n;
+        rskip += n;
+        if lskip == l.len() {
+            left.pop();
+            lskip = 0;
+        }
+        if rskip == r.len() {
+            right.pop();
+            rskip = 0;
+        }
+    }
+}
+
+pub struct ZipCompare<'a> {
+    left: Chars<'a>,
+    right: Chars<'a>,
+    pos: usize,
+    // length of the common prefix, reported as the first run
+    prefix: usize,
+    pending: Option<(Option<char>, Option<char>)>,
+}
+
+impl<'a> ZipCompare<'a> {
+    fn pair(&mut self) -> Option<(Option<char>, Option<char>)> {
+        if let Some(pair) = self.pending.take() {
+            return Some(pair);
+        }
+        match (self.left.next(), self.right.next()) {
+            (None, None) => None,
+            pair => Some(pair),
+        }
+    }
+}
+
+impl<'a> Iterator for ZipCompare<'a> {
+    type Item = Run;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.prefix > 0 {
+            let range = 0..self.prefix;
+            self.prefix = 0;
+            return Some(Run { range, equal: true });
+        }
+        let (l, r) = self.pair()?;
+        let equal = l.is_some() && l == r;
+        let start = self.pos;
+        self.pos += 1;
+
+        while let Some((l, r)) = self.pair() {
+            if (l.is_some() && l == r) != equal {
+                self.pending = Some((l, r));
+                break;
+            }
+            self.pos += 1;
+        }
+
+        Some(Run {
+            range: start..self.pos,
+            equal,
+        })
+    }
+}
+
+/// Content equality. Subtrees shared by both ropes are skipped without
+/// looking at their text.
+impl PartialEq for Rope {
+    fn eq(&self, other: &Rope) -> bool {
+        self.len() == other.len() && common_len(self, other, false) == self.len()
+    }
+}
+
+impl Eq for Rope {}
+
+impl PartialEq<str> for Rope {
+    fn eq(&self, other: &str) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let mut rest = other.as_bytes();
+        self.chunks().all(|chunk| {
+            let (head, tail) = rest.split_at(chunk.len());
+            rest = tail;
+            head == chunk.as_bytes()
+        })
+    }
+}
+
+impl PartialEq<&str> for Rope {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Rope {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Rope> for str {
+    fn eq(&self, other: &Rope) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<Rope> for &str {
+    fn eq(&self, other: &Rope) -> bool {
+        other == *self
+    }
+}
+
+impl PartialEq<Rope> for String {
+    fn eq(&self, other: &Rope) -> bool {
+        other == self.as_str()
+    }
+}
+
+/// Lexicographic order by bytes, which for UTF-8 is the same as by chars and
+/// matches the order of `str`. The common prefix is found skipping shared
+/// subtrees, and neither rope is copied into a string.
+impl Ord for Rope {
+    fn cmp(&self, other: &Rope) -> Ordering {
+        let common = common_len(self, other, false);
+        bytes_from(self, common).cmp(bytes_from(other, common))
+    }
+}
+
+impl PartialOrd for Rope {
+    fn partial_cmp(&self, other: &Rope) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialOrd<str> for Rope {
+    fn partial_cmp(&self, other: &str) -> Option<Ordering> {
+        Some(bytes_from(self, 0).cmp(other.bytes()))
+    }
+}
+
+impl PartialOrd<&str> for Rope {
+    fn partial_cmp(&self, other: &&str) -> Option<Ordering> {
+        self.partial_cmp(*other)
+    }
+}
+
+impl PartialOrd<String> for Rope {
+    fn partial_cmp(&self, other: &String) -> Option<Ordering> {
+        self.partial_cmp(other.as_str())
+    }
+}
+
+impl PartialOrd<Rope> for str {
+    fn partial_cmp(&self, other: &Rope) -> Option<Ordering> {
+        other.partial_cmp(self).map(Ordering::reverse)
+    }
+}
+
+impl PartialOrd<Rope> for &str {
+    fn partial_cmp(&self, other: &Rope) -> Option<Ordering> {
+        other.partial_cmp(*self).map(Ordering::reverse)
+    }
+}
+
+impl PartialOrd<Rope> for String {
+    fn partial_cmp(&self, other: &Rope) -> Option<Ordering> {
+        other.partial_cmp(self.as_str()).map(Ordering::reverse)
+    }
+}
+
+// size of the blocks `Hash for Rope` feeds the hasher
+const HASH_BLOCK: usize = 256;
+
+/// Hashes the content whatever the shape of the tree: the bytes are fed to
+/// the hasher in fixed-size blocks rather than chunk by chunk, so equal
+/// ropes hash alike with any hasher. With a hasher streaming its writes,
+/// like the standard `DefaultHasher`, a rope also hashes like an equal
+/// string.
+impl Hash for Rope {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut block = [0; HASH_BLOCK];
+        let mut filled = 0;
+        for chunk in self.chunks() {
+            let mut bytes = chunk.as_bytes();
+            while !bytes.is_empty() {
+                let n = bytes.len().min(HASH_BLOCK - filled);
+                block[filled..filled + n].copy_from_slice(&bytes[..n]);
+                filled += n;
+                bytes = &bytes[n..];
+                if filled == HASH_BLOCK {
+                    state.write(&block);
+                    filled = 0;
+                }
+            }
+        }
+        state.write(&block[..filled]);
+        // same terminator as `str`, so "ab", "c" and "a", "bc" differ
+        state.write_u8(0xff);
+    }
+}
+
+// the bytes of `rope` from byte offset `at` on
+fn bytes_from(rope: &Rope, at: usize) -> impl Iterator<Item = u8> + '_ {
+    let (chunks, skip) = rope.chunks_at_byte(at);
+    chunks
+        .enumerate()
+        .flat_map(move |(i, chunk)| chunk.as_bytes()[if i == 0 { skip } else { 0 }..].iter())
+        .copied()
+}
+
+#[test]
+fn test_zip_compare() {
+    let a = Rope::new("Hello, World!");
+    let b = Rope::new("Hello, Wyrld!!");
+
+    let runs: Vec<Run> = a.zip_compare(&b).collect();
+    assert_eq!(
+        runs,
+        vec![
+            Run {
+                range: 0..8,
+                equal: true
+            },
+            Run {
+                range: 8..9,
+                equal: false
+            },
+            Run {
+                range: 9..13,
+                equal: true
+            },
+            Run {
+                range: 13..14,
+                equal: false
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_first_difference() {
+    let a = Rope::new("Hello, World!");
+    let b = Rope::new("Hello, World!");
+    let c = Rope::new("Help");
+
+    assert_eq!(a.first_difference(&b), None);
+    assert_eq!(a.first_difference(&c), Some(3));
+}
+
+#[test]
+fn test_ptr_eq_subtree() {
+    let rope = Rope::from_leaves(vec![Rope::new("Hello,"), Rope::new(" World!")]);
+    let same = rope.share();
+    let copy = Rope::from_leaves(vec![Rope::new("Hello,"), Rope::new(" World!")]);
+
+    assert!(rope.ptr_eq_subtree(&same));
+    assert!(!rope.ptr_eq_subtree(&copy));
+    assert_eq!(rope.first_difference(&copy), None);
+}
+
+#[test]
+fn test_first_difference_snapshots() {
+    let text = "abcdefgh".repeat(1024);
+    let mut builder = crate::RopeBuilder::new();
+    builder.append(&text);
+    let rope = builder.finish();
+
+    let mut edited = rope.share();
+    edited.insert(5000, "X");
+    assert_eq!(rope.first_difference(&edited), Some(5000));
+    assert_eq!(edited.first_difference(&rope), Some(5000));
+
+    let runs: Vec<Run> = rope.zip_compare(&edited).collect();
+    assert_eq!(
+        runs[0],
+        Run {
+            range: 0..5000,
+            equal: true
+        }
+    );
+    assert_eq!(runs[1].range.start, 5000);
+}
+
+#[test]
+fn test_changes_since() {
+    let text = "abcdefgh".repeat(1024);
+    let mut builder = crate::RopeBuilder::new();
+    builder.append(&text);
+    let snapshot = builder.finish();
+
+    let mut edited = snapshot.share();
+    edited.insert(5000, "XY");
+    edited.delete(6000, 6009);
+    let changes: Vec<ChangedRange> = edited.changes_since(&snapshot).collect();
+    assert_eq!(
+        changes,
+        vec![ChangedRange {
+            old: 5000..6008,
+            new: 5000..6000,
+        }]
+    );
+
+    assert_eq!(snapshot.share().changes_since(&snapshot).count(), 0);
+}
+
+#[test]
+fn test_rope_eq() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+
+    assert_eq!(rope, "Grüße, World!");
+    assert_eq!(rope, String::from("Grüße, World!"));
+    assert!("Grüße, World!" == rope);
+    assert_ne!(rope, "Grüße, World?");
+    assert_ne!(rope, "Grüße");
+
+    // same text, different leaves
+    assert_eq!(rope, Rope::new("Grüße, World!"));
+    assert_eq!(rope, rope.share());
+    assert_ne!(rope, Rope::new("Grüße, World?"));
+    assert_eq!(Rope::empty(), "");
+}
+
+#[test]
+fn test_rope_ord() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+
+    assert!(rope < Rope::new("Grüße, Worlds"));
+    assert!(rope > Rope::new("Grüße"));
+    assert!(rope < Rope::new("Grüßf"));
+    assert_eq!(rope.cmp(&Rope::new("Grüße, World!")), Ordering::Equal);
+    assert!(Rope::empty() < rope);
+
+    // same lead byte, the order is decided past the common prefix
+    assert!(Rope::new("aé") < Rope::new("aê"));
+
+    assert!(rope < "Grüße, Worlds");
+    assert!(rope > "Grüße");
+    assert!("Grüße" < rope);
+    let last = String::from("Z");
+    assert!(last > rope);
+    assert_eq!(rope.partial_cmp("Grüße, World!"), Some(Ordering::Equal));
+
+    let mut sorted = vec![Rope::new("pear"), Rope::new("apple"), rope.share()];
+    sorted.sort();
+    assert_eq!(sorted, vec!["Grüße, World!", "apple", "pear"]);
+
+    let mut map = std::collections::BTreeMap::new();
+    map.insert(Rope::new("b"), 2);
+    map.insert(Rope::new("a"), 1);
+    assert_eq!(map.keys().next().unwrap(), "a");
+}
+
+#[test]
+fn test_rope_hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+
+    fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let text = "Grüße, World!".repeat(200);
+    let chunked = Rope::from_leaves(vec![Rope::new("Grü"), Rope::new(&text[4..])]);
+    let mut builder = crate::RopeBuilder::new();
+    builder.append(&text);
+    let built = builder.finish();
+
+    assert_eq!(hash(&chunked), hash(text.as_str()));
+    assert_eq!(hash(&built), hash(text.as_str()));
+    assert_eq!(hash(&Rope::empty()), hash(""));
+    assert_ne!(hash(&Rope::new("ab")), hash(&Rope::new("abc")));
+
+    let mut set = HashSet::new();
+    set.insert(chunked);
+    assert!(set.contains(&built));
+    assert!(!set.contains(&Rope::new("Grüße")));
+}
+
+#[test]
+fn test_rope_hash_ignores_chunking() {
+    // a hasher that, unlike `DefaultHasher`, tells writes apart
+    #[derive(Default)]
+    struct PerWrite(u64);
+
+    impl Hasher for PerWrite {
+        fn write(&mut self, bytes: &[u8]) {
+            let sum = bytes
+                .iter()
+                .fold(0u64, |h, &b| h.wrapping_mul(31) ^ u64::from(b));
+            self.0 = (self.0.rotate_left(7) ^ bytes.len() as u64).wrapping_add(sum);
+        }
+
+        fn finish(&self) -> u64 {
+            self.0
+        }
+    }
+
+    fn hash(rope: &Rope) -> u64 {
+        let mut hasher = PerWrite::default();
+        rope.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let text = "Grüße, World!".repeat(100);
+    let one = Rope::new(&text);
+    let split = Rope::from_leaves(vec![
+        Rope::new("G"),
+        Rope::new(&text[1..700]),
+        Rope::new(&text[700..]),
+    ]);
+
+    assert_eq!(hash(&one), hash(&split));
+    assert_ne!(hash(&one), hash(&Rope::new(&text[1..])));
+}
+
+#[test]
+fn test_same_root_and_sharing() {
+    let text = "abcdefgh".repeat(1024);
+    let mut builder = crate::RopeBuilder::new();
+    builder.append(&text);
+    let rope = builder.finish();
+
+    let snapshot = rope.clone();
+    assert!(Rope::same_root(&rope, &snapshot));
+    assert_eq!(rope.shared_len(&snapshot), rope.len());
+
+    let mut edited = rope.clone();
+    edited.insert(100, "X");
+    assert!(!Rope::same_root(&rope, &edited));
+    assert!(edited.shares_subtree(&rope));
+    // everything but the leaf that was edited is still shared
+    let shared = edited.shared_len(&rope);
+    assert!(shared < rope.len() && shared >= rope.len() - 1024);
+
+    let copy = Rope::new(&text);
+    assert!(!copy.shares_subtree(&rope));
+    assert_eq!(copy, rope);
+}
@@ -0,0 +1,245 @@
+//! A thin wrapper mirroring the most used methods of `ropey::Rope`, to ease
+//! moving an editor over from ropey: swap `use ropey::Rope` for
+//! `use rope::compat::Rope` and port the remaining calls at leisure.
+//!
+//! As in ropey, all offsets are in chars unless the method name says
+//! otherwise, and out of bounds offsets panic.
+
+use std::fmt;
+use std::ops::{Bound, Range, RangeBounds};
+
+use crate::rope::{Chars, Chunks, Rope as Inner};
+
+#[derive(Debug)]
+pub struct Rope(Inner);
+
+impl Rope {
+    pub fn new() -> Rope {
+        Rope(Inner::empty())
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(text: &str) -> Rope {
+        let mut rope = Inner::empty();
+        rope.insert(0, text);
+        Rope(rope)
+    }
+
+    /// The wrapped rope.
+    pub fn inner(&self) -> &Inner {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Inner {
+        self.0
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        self.0.len_bytes()
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.0.len_chars()
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.0.len_lines()
+    }
+
+    pub fn len_utf16_cu(&self) -> usize {
+        self.0.len_utf16()
+    }
+
+    pub fn insert(&mut self, char_idx: usize, text: &str) {
+        self.check_char(char_idx);
+        self.0.insert(self.0.char_to_byte(char_idx), text);
+    }
+
+    pub fn insert_char(&mut self, char_idx: usize, ch: char) {
+        self.check_char(char_idx);
+        self.0.insert_char(self.0.char_to_byte(char_idx), ch);
+    }
+
+    pub fn remove<R: RangeBounds<usize>>(&mut self, char_range: R) {
+        let range = self.char_range(char_range);
+        let start = self.0.char_to_byte(range.start);
+        let end = self.0.char_to_byte(range.end);
+        self.0.remove(start..end);
+    }
+
+    /// Splits the rope at `char_idx`, keeping the first part and returning
+    /// the second.
+    pub fn split_off(&mut self, char_idx: usize) -> Rope {
+        self.check_char(char_idx);
+        Rope(self.0.split_off(self.0.char_to_byte(char_idx)))
+    }
+
+    pub fn append(&mut self, other: Rope) {
+        self.0.append(other.0);
+    }
+
+    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
+        assert!(byte_idx <= self.len_bytes(), "byte index out of bounds");
+        self.0.byte_to_char(byte_idx)
+    }
+
+    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+        self.check_char(char_idx);
+        self.0.char_to_byte(char_idx)
+    }
+
+    pub fn char_to_line(&self, char_idx: usize) -> usize {
+        self.check_char(char_idx);
+        self.0.char_to_line(char_idx)
+    }
+
+    pub fn line_to_char(&self, line_idx: usize) -> usize {
+        assert!(line_idx <= self.len_lines(), "line index out of bounds");
+        self.0.line_to_char(line_idx)
+    }
+
+    pub fn byte_to_line(&self, byte_idx: usize) -> usize {
+        self.char_to_line(self.byte_to_char(byte_idx))
+    }
+
+    pub fn line_to_byte(&self, line_idx: usize) -> usize {
+        self.char_to_byte(self.line_to_char(line_idx))
+    }
+
+    pub fn char_to_utf16_cu(&self, char_idx: usize) -> usize {
+        self.check_char(char_idx);
+        self.0.char_to_utf16(char_idx)
+    }
+
+    pub fn utf16_cu_to_char(&self, utf16_cu_idx: usize) -> usize {
+        assert!(
+            utf16_cu_idx <= self.len_utf16_cu(),
+            "UTF-16 index out of bounds"
+        );
+        self.0.utf16_to_char(utf16_cu_idx)
+    }
+
+    pub fn byte(&self, byte_idx: usize) -> u8 {
+        assert!(byte_idx < self.len_bytes(), "byte index out of bounds");
+        self.0.byte(byte_idx)
+    }
+
+    pub fn char(&self, char_idx: usize) -> char {
+        assert!(char_idx < self.len_chars(), "char index out of bounds");
+        self.0
+            .chars_at(char_idx)
+            .next()
+            .expect("char index in bounds")
+    }
+
+    /// The line at `line_idx`, including its line break. Unlike ropey this
+    /// returns a rope sharing its leaves with self rather than a slice.
+    pub fn line(&self, line_idx: usize) -> Rope {
+        assert!(line_idx < self.len_lines(), "line index out of bounds");
+        let start = self.0.line_to_char(line_idx);
+        let end = self.0.line_to_char(line_idx + 1);
+        Rope(self.0.slice(start..end).to_rope())
+    }
+
+    /// The chars in `char_range`, as a rope sharing its leaves with self.
+    pub fn slice<R: RangeBounds<usize>>(&self, char_range: R) -> Rope {
+        Rope(self.0.slice(self.char_range(char_range)).to_rope())
+    }
+
+    pub fn chars(&self) -> Chars<'_> {
+        self.0.chars()
+    }
+
+    pub fn chars_at(&self, char_idx: usize) -> Chars<'_> {
+        self.check_char(char_idx);
+        self.0.chars_at(char_idx)
+    }
+
+    pub fn chunks(&self) -> Chunks<'_> {
+        self.0.chunks()
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = Rope> + '_ {
+        // a text ending in a line break has an empty last line, as in ropey
+        (0..self.len_lines()).map(move |line| self.line(line))
+    }
+
+    fn check_char(&self, char_idx: usize) {
+        assert!(char_idx <= self.len_chars(), "char index out of bounds");
+    }
+
+    fn char_range<R: RangeBounds<usize>>(&self, range: R) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n + 1,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len_chars(),
+        };
+        assert!(start <= end, "char range starts after it ends");
+        self.check_char(end);
+        start..end
+    }
+}
+
+impl Default for Rope {
+    fn default() -> Rope {
+        Rope::new()
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Rope {
+        Rope::from_str(text)
+    }
+}
+
+impl From<Inner> for Rope {
+    fn from(rope: Inner) -> Rope {
+        Rope(rope)
+    }
+}
+
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[test]
+fn test_ropey_style_edits() {
+    let mut rope = Rope::from_str("Hello wörld!\nline two\n");
+    rope.insert(5, ",");
+    rope.insert_char(12, '?');
+    rope.remove(13..14);
+    assert_eq!(rope.to_string(), "Hello, wörld?\nline two\n");
+
+    assert_eq!(rope.len_chars(), 23);
+    assert_eq!(rope.len_bytes(), 24);
+    assert_eq!(rope.len_lines(), 3);
+    assert_eq!(rope.char(8), 'ö');
+    assert_eq!(rope.byte(9), 0xb6);
+    assert_eq!(rope.char_to_line(14), 1);
+    assert_eq!(rope.line_to_byte(1), 15);
+    assert_eq!(rope.line(1).to_string(), "line two\n");
+    assert_eq!(rope.slice(7..=11).to_string(), "wörld");
+
+    let lines: Vec<String> = rope.lines().map(|line| line.to_string()).collect();
+    assert_eq!(lines, vec!["Hello, wörld?\n", "line two\n", ""]);
+
+    let tail = rope.split_off(14);
+    assert_eq!(rope.to_string(), "Hello, wörld?\n");
+    assert_eq!(tail.to_string(), "line two\n");
+    rope.append(tail);
+    assert_eq!(rope.len_lines(), 3);
+}
+
+#[test]
+#[should_panic]
+fn test_ropey_style_bounds() {
+    let mut rope = Rope::from("abc");
+    rope.insert(4, "d");
+}
@@ -0,0 +1,134 @@
+use std::iter::FromIterator;
+use std::sync::OnceLock;
+
+use crate::rope::Rope;
+
+/// Several ropes, such as the cells of a notebook or included files, read
+/// as one document. Each part is edited on its own; reading the whole goes
+/// through `rope`, which stitches the parts together on first use and shares
+/// their leaves, so it costs O(k log n) for k parts and no copying.
+///
+/// Offsets are char offsets.
+#[derive(Debug, Clone, Default)]
+pub struct CompositeRope {
+    parts: Vec<Rope>,
+    // char offset each part starts at in the whole
+    starts: Vec<usize>,
+    whole: OnceLock<Rope>,
+}
+
+impl CompositeRope {
+    pub fn new() -> CompositeRope {
+        CompositeRope::default()
+    }
+
+    /// Adds `part` at the end of the document.
+    pub fn push(&mut self, part: Rope) {
+        self.starts.push(self.len_chars());
+        self.parts.push(part);
+        self.whole = OnceLock::new();
+    }
+
+    /// Number of parts.
+    pub fn len_parts(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn part(&self, i: usize) -> &Rope {
+        &self.parts[i]
+    }
+
+    pub fn parts(&self) -> &[Rope] {
+        &self.parts
+    }
+
+    /// Length of the whole document in chars.
+    pub fn len_chars(&self) -> usize {
+        match (self.starts.last(), self.parts.last()) {
+            (Some(start), Some(part)) => start + part.len_chars(),
+            _ => 0,
+        }
+    }
+
+    /// Applies `edit` to part `i`.
+    pub fn edit_part<T, F: FnOnce(&mut Rope) -> T>(&mut self, i: usize, edit: F) -> T {
+        let result = edit(&mut self.parts[i]);
+        let mut start = self.starts[i];
+        for (part, offset) in self.parts[i..].iter().zip(&mut self.starts[i..]) {
+            *offset = start;
+            start += part.len_chars();
+        }
+        self.whole = OnceLock::new();
+        result
+    }
+
+    /// The part holding char offset `offset` of the whole, and the offset
+    /// in it. An offset between two parts belongs to the second one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no parts or `offset` is past the end.
+    pub fn to_part(&self, offset: usize) -> (usize, usize) {
+        assert!(!self.parts.is_empty(), "no parts");
+        assert!(offset <= self.len_chars(), "offset out of bounds");
+        let i = self.starts.partition_point(|&start| start <= offset) - 1;
+        (i, offset - self.starts[i])
+    }
+
+    /// The offset in the whole of char offset `offset` of part `part`.
+    pub fn from_part(&self, part: usize, offset: usize) -> usize {
+        self.starts[part] + offset.min(self.parts[part].len_chars())
+    }
+
+    /// The whole document as one rope, for all the read methods of `Rope`.
+    pub fn rope(&self) -> &Rope {
+        self.whole.get_or_init(|| {
+            let shared = self.parts.iter().map(Rope::share);
+            shared.fold(Rope::empty(), Rope::concat)
+        })
+    }
+}
+
+impl FromIterator<Rope> for CompositeRope {
+    fn from_iter<I: IntoIterator<Item = Rope>>(parts: I) -> CompositeRope {
+        let mut composite = CompositeRope::new();
+        for part in parts {
+            composite.push(part);
+        }
+        composite
+    }
+}
+
+#[test]
+fn test_composite_rope() {
+    let mut doc: CompositeRope = vec![
+        Rope::new("# Title\n"),
+        Rope::empty(),
+        Rope::new("print(1)\n"),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(doc.len_chars(), 17);
+    assert_eq!(doc.rope().to_string(), "# Title\nprint(1)\n");
+    assert_eq!(doc.rope().len_lines(), 3);
+    assert_eq!(doc.to_part(3), (0, 3));
+    assert_eq!(doc.to_part(8), (2, 0));
+    assert_eq!(doc.to_part(17), (2, 9));
+    assert_eq!(doc.from_part(2, 6), 14);
+    assert_eq!(
+        doc.rope().slice_to_string(doc.from_part(2, 0)..),
+        "print(1)\n"
+    );
+
+    doc.edit_part(0, |cell| cell.insert(2, "Big "));
+    doc.edit_part(1, |cell| cell.insert(0, "x = 1\n"));
+    assert_eq!(doc.rope().to_string(), "# Big Title\nx = 1\nprint(1)\n");
+    assert_eq!(doc.to_part(14), (1, 2));
+    assert_eq!(doc.from_part(2, 0), 18);
+    assert_eq!(doc.part(2).to_string(), "print(1)\n");
+
+    doc.push(Rope::new("done"));
+    assert_eq!(doc.len_parts(), 4);
+    assert_eq!(doc.rope().rfind_from(doc.len_chars(), "d"), Some(27));
+}
@@ -0,0 +1,73 @@
+//! Counting helpers for leaf metadata. With the `simd` feature the counts
+//! are done eight bytes at a time with plain integer ops (SWAR), which works
+//! on every target without pulling in a dependency.
+
+/// Number of line feeds in `s`.
+pub(crate) fn newlines(s: &str) -> usize {
+    #[cfg(feature = "simd")]
+    return swar::count_matching(s.as_bytes(), b'\n');
+    #[cfg(not(feature = "simd"))]
+    return s.bytes().filter(|&b| b == b'\n').count();
+}
+
+/// Number of chars in `s`.
+pub(crate) fn chars(s: &str) -> usize {
+    #[cfg(feature = "simd")]
+    return s.len() - swar::count_continuation(s.as_bytes());
+    #[cfg(not(feature = "simd"))]
+    return s.chars().count();
+}
+
+#[cfg(feature = "simd")]
+mod swar {
+    use std::convert::TryInto;
+
+    const LO7: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+    const HI: u64 = 0x8080_8080_8080_8080;
+
+    fn words(bytes: &[u8]) -> (impl Iterator<Item = u64> + '_, &[u8]) {
+        let chunks = bytes.chunks_exact(8);
+        let rest = chunks.remainder();
+        let words = chunks.map(|c| u64::from_ne_bytes(c.try_into().expect("8 bytes")));
+        (words, rest)
+    }
+
+    pub(super) fn count_matching(bytes: &[u8], needle: u8) -> usize {
+        let pattern = u64::from_ne_bytes([needle; 8]);
+        let (words, rest) = words(bytes);
+        let mut count = 0;
+        for word in words {
+            let x = word ^ pattern;
+            // high bit set exactly in the bytes of x that are zero
+            let zero = !(((x & LO7) + LO7) | x | LO7);
+            count += zero.count_ones() as usize;
+        }
+        count + rest.iter().filter(|&&b| b == needle).count()
+    }
+
+    // bytes of the form 0b10xxxxxx
+    pub(super) fn count_continuation(bytes: &[u8]) -> usize {
+        let (words, rest) = words(bytes);
+        let mut count = 0;
+        for word in words {
+            count += (word & !(word << 1) & HI).count_ones() as usize;
+        }
+        count + rest.iter().filter(|&&b| b & 0xc0 == 0x80).count()
+    }
+}
+
+#[test]
+fn test_counts() {
+    let samples = [
+        "",
+        "a",
+        "\n",
+        "line\nline\n",
+        "Grüße,\nWörld!\n日本語\n🦀\n\n",
+        "\n\n\n\n\n\n\n\n\n",
+    ];
+    for s in samples.iter().map(|s| s.repeat(3)) {
+        assert_eq!(newlines(&s), s.matches('\n').count(), "{:?}", s);
+        assert_eq!(chars(&s), s.chars().count(), "{:?}", s);
+    }
+}
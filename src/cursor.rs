@@ -0,0 +1,157 @@
+use crate::rope::Rope;
+
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// A caret position in a rope, in chars.
+///
+/// Vertical movement remembers the column the cursor wants to be on, so
+/// moving across a short line and back returns to the original column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    offset: usize,
+    goal: Option<usize>,
+    tab_width: usize,
+}
+
+impl Cursor {
+    pub fn new(offset: usize) -> Cursor {
+        Cursor {
+            offset,
+            goal: None,
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+
+    pub fn with_tab_width(mut self, tab_width: usize) -> Cursor {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Moves to `offset`, forgetting the goal column.
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+        self.goal = None;
+    }
+
+    /// Display column of the cursor within its line, with tabs expanded.
+    pub fn column(&self, rope: &Rope) -> usize {
+        let start = rope.line_to_char(rope.char_to_line(self.offset));
+        let mut col = 0;
+        for c in rope.chars_at(start).take(self.offset - start) {
+            col = self.advance(col, c);
+        }
+        col
+    }
+
+    pub fn move_up(&mut self, rope: &Rope) {
+        let line = rope.char_to_line(self.offset);
+        if line > 0 {
+            self.move_to_line(rope, line - 1);
+        }
+    }
+
+    pub fn move_down(&mut self, rope: &Rope) {
+        let line = rope.char_to_line(self.offset);
+        if line + 1 < rope.len_lines() {
+            self.move_to_line(rope, line + 1);
+        }
+    }
+
+    fn move_to_line(&mut self, rope: &Rope, line: usize) {
+        let goal = match self.goal {
+            Some(goal) => goal,
+            None => self.column(rope),
+        };
+
+        let mut offset = rope.line_to_char(line);
+        let mut col = 0;
+        for c in rope.line_chars(line) {
+            let next = self.advance(col, c);
+            if next > goal {
+                break;
+            }
+            col = next;
+            offset += 1;
+        }
+        self.offset = offset;
+        self.goal = Some(goal);
+    }
+
+    /// Transposes the chars around the cursor and moves past them.
+    pub fn transpose_chars(&mut self, rope: &Rope) -> Rope {
+        let edited = rope.transpose_chars(self.offset);
+        if let Some(at) = rope.char_pair(self.offset) {
+            self.set_offset(at + 2);
+        }
+        edited
+    }
+
+    /// Transposes the words around the cursor and moves past the second one.
+    pub fn transpose_words(&mut self, rope: &Rope) -> Rope {
+        let edited = rope.transpose_words(self.offset);
+        if let Some((_, second)) = rope.word_pair(self.offset) {
+            self.set_offset(second.end);
+        }
+        edited
+    }
+
+    fn advance(&self, col: usize, c: char) -> usize {
+        if c == '\t' {
+            return (col / self.tab_width + 1) * self.tab_width;
+        }
+        col + 1
+    }
+}
+
+#[test]
+fn test_cursor_goal_column() {
+    let rope = Rope::new("a long line\nab\nanother line");
+    let mut cursor = Cursor::new(8);
+
+    cursor.move_down(&rope);
+    assert_eq!(cursor.offset(), 14);
+
+    cursor.move_down(&rope);
+    assert_eq!(cursor.offset(), 23);
+    assert_eq!(cursor.column(&rope), 8);
+
+    cursor.move_down(&rope);
+    assert_eq!(cursor.offset(), 23);
+
+    cursor.move_up(&rope);
+    cursor.move_up(&rope);
+    assert_eq!(cursor.offset(), 8);
+}
+
+#[test]
+fn test_cursor_tabs() {
+    let rope = Rope::new("\tx = 1\nabcdefgh");
+    let mut cursor = Cursor::new(13);
+    assert_eq!(cursor.column(&rope), 6);
+
+    cursor.move_up(&rope);
+    assert_eq!(cursor.offset(), 3);
+    assert_eq!(cursor.column(&rope), 6);
+
+    cursor.set_offset(9);
+    cursor.move_up(&rope);
+    assert_eq!(cursor.offset(), 0);
+}
+
+#[test]
+fn test_cursor_transpose() {
+    let rope = Rope::new("abc def");
+    let mut cursor = Cursor::new(1);
+
+    let rope = cursor.transpose_chars(&rope);
+    assert_eq!(rope.chars().collect::<String>(), "bac def");
+    assert_eq!(cursor.offset(), 2);
+
+    let rope = cursor.transpose_words(&rope);
+    assert_eq!(rope.chars().collect::<String>(), "def bac");
+    assert_eq!(cursor.offset(), 7);
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::rope::Rope;
+
+// longest leaf text shown in a diff line
+const PREVIEW_CHARS: usize = 16;
+
+impl Rope {
+    /// Describes where the trees of two ropes differ in shape or metadata,
+    /// regardless of whether their text is the same: one line per diverging
+    /// position, down to `max_depth` levels. Subtrees shared by both ropes are
+    /// skipped. Meant for debugging balance and weight issues; the format is
+    /// not stable.
+    ///
+    /// Positions are written as paths from the root, `root.l.r` being the
+    /// right child of the left child of the root.
+    pub fn structural_diff(&self, other: &Rope, max_depth: usize) -> String {
+        let mut out = String::new();
+        diff(self, other, &mut "root".to_string(), max_depth, &mut out);
+        out
+    }
+
+    /// Draws the tree one node per line, indented by depth, with the
+    /// weights and depth of each node and the start of the text of each
+    /// leaf. Meant for debugging; the format is not stable.
+    pub fn dump_structure(&self) -> String {
+        let mut out = String::new();
+        dump(self, "", 0, &mut out);
+        out
+    }
+}
+
+impl Rope {
+    /// Describes the tree in the DOT language of Graphviz, with the weights
+    /// of each node and the length and start of the text of each leaf.
+    /// Subtrees used more than once, as after `repeat`, are drawn once with
+    /// several edges into them. Render with `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph rope {\n    node [shape=box];\n");
+        dot(self, &mut HashMap::new(), &mut out);
+        out.push_str("}\n");
+        out
+    }
+}
+
+// writes the node and what is below it unless already written, and returns
+// its id
+fn dot(rope: &Rope, ids: &mut HashMap<*const Rope, usize>, out: &mut String) -> usize {
+    if let Some(&id) = ids.get(&(rope as *const Rope)) {
+        return id;
+    }
+    let id = ids.len();
+    ids.insert(rope, id);
+    match rope {
+        Rope::Leaf(leaf) => {
+            let text = leaf.as_str();
+            writeln!(
+                out,
+                "    n{} [shape=ellipse, label=\"bytes={}\\n{}\"];",
+                id,
+                text.len(),
+                preview(text).escape_debug()
+            )
+            .unwrap();
+        }
+        Rope::Node(node) => {
+            writeln!(
+                out,
+                "    n{} [label=\"weight={}\\nbytes={}\\nchars={}\\ndepth={}\"];",
+                id,
+                node.weight.get(),
+                node.bytes.get(),
+                node.chars.get(),
+                node.depth
+            )
+            .unwrap();
+            for (child, side) in [(&node.left, "l"), (&node.right, "r")].iter() {
+                if let Some(child) = child {
+                    let child = dot(child, ids, out);
+                    writeln!(out, "    n{} -> n{} [label={}];", id, child, side).unwrap();
+                }
+            }
+        }
+    }
+    id
+}
+
+fn dump(rope: &Rope, label: &str, indent: usize, out: &mut String) {
+    writeln!(
+        out,
+        "{:indent$}{}{}",
+        "",
+        label,
+        describe(rope),
+        indent = indent
+    )
+    .unwrap();
+    if let Rope::Node(node) = rope {
+        let children = [(&node.left, "l: "), (&node.right, "r: ")];
+        for (child, label) in children.iter() {
+            match child.as_deref() {
+                Some(child) => dump(child, label, indent + 2, out),
+                None => {
+                    writeln!(out, "{:indent$}{}missing", "", label, indent = indent + 2).unwrap()
+                }
+            }
+        }
+    }
+}
+
+fn diff(a: &Rope, b: &Rope, path: &mut String, depth: usize, out: &mut String) {
+    if a.ptr_eq_subtree(b) {
+        return;
+    }
+    match (a, b) {
+        (Rope::Leaf(x), Rope::Leaf(y)) => {
+            if x.as_str() != y.as_str() {
+                writeln!(out, "{}: {} vs {}", path, describe(a), describe(b)).unwrap();
+            }
+        }
+        (Rope::Node(x), Rope::Node(y)) => {
+            if (x.weight, x.bytes, x.chars, x.depth) != (y.weight, y.bytes, y.chars, y.depth) {
+                writeln!(out, "{}: {} vs {}", path, describe(a), describe(b)).unwrap();
+            }
+            if depth == 0 {
+                writeln!(out, "{}: not compared below", path).unwrap();
+                return;
+            }
+            let children = [(&x.left, &y.left, ".l"), (&x.right, &y.right, ".r")];
+            for (l, r, step) in children.iter() {
+                let len = path.len();
+                path.push_str(step);
+                match (l.as_deref(), r.as_deref()) {
+                    (Some(l), Some(r)) => diff(l, r, path, depth - 1, out),
+                    (None, None) => {}
+                    (l, r) => writeln!(out, "{}: {} vs {}", path, child(l), child(r)).unwrap(),
+                }
+                path.truncate(len);
+            }
+        }
+        _ => writeln!(out, "{}: {} vs {}", path, describe(a), describe(b)).unwrap(),
+    }
+}
+
+fn describe(rope: &Rope) -> String {
+    match rope {
+        Rope::Leaf(leaf) => {
+            let text = leaf.as_str();
+            format!("leaf(bytes={}, {:?})", text.len(), preview(text))
+        }
+        Rope::Node(node) => format!(
+            "node(weight={}, bytes={}, chars={}, depth={})",
+            node.weight.get(),
+            node.bytes.get(),
+            node.chars.get(),
+            node.depth
+        ),
+    }
+}
+
+// the start of a leaf, with an ellipsis if cut
+fn preview(text: &str) -> String {
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < text.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+fn child(rope: Option<&Rope>) -> String {
+    rope.map_or_else(|| "missing".to_string(), describe)
+}
+
+#[test]
+fn test_structural_diff() {
+    let a = Rope::from_leaves(vec![Rope::new("Hello"), Rope::new(", World!")]);
+    let b = Rope::from_leaves(vec![Rope::new("Hello,"), Rope::new(" World!")]);
+    assert_eq!(a.structural_diff(&a.share(), 8), "");
+
+    assert_eq!(
+        a.structural_diff(&b, 8),
+        "root: node(weight=5, bytes=13, chars=13, depth=1) \
+         vs node(weight=6, bytes=13, chars=13, depth=1)\n\
+         root.l: leaf(bytes=5, \"Hello\") vs leaf(bytes=6, \"Hello,\")\n\
+         root.r: leaf(bytes=8, \", World!\") vs leaf(bytes=7, \" World!\")\n"
+    );
+
+    let flat = Rope::new("Hello, World!");
+    assert_eq!(
+        a.structural_diff(&flat, 8),
+        "root: node(weight=5, bytes=13, chars=13, depth=1) \
+         vs leaf(bytes=13, \"Hello, World!\")\n"
+    );
+}
+
+#[test]
+fn test_structural_diff_depth_limit() {
+    let a = Rope::from_leaves(vec![Rope::new("a"), Rope::new("b"), Rope::new("c")]);
+    let b = Rope::from_leaves(vec![Rope::new("a"), Rope::new("x"), Rope::new("c")]);
+
+    assert_eq!(a.structural_diff(&b, 1), "root.l: not compared below\n");
+    assert_eq!(
+        a.structural_diff(&b, 2),
+        "root.l.r: leaf(bytes=1, \"b\") vs leaf(bytes=1, \"x\")\n"
+    );
+}
+
+#[test]
+fn test_dump_structure() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("a"),
+        Rope::new("b"),
+        Rope::new("Hello, World! Hello!"),
+    ]);
+    assert_eq!(
+        rope.dump_structure(),
+        "node(weight=2, bytes=22, chars=22, depth=2)
+  l: node(weight=1, bytes=2, chars=2, depth=1)
+    l: leaf(bytes=1, \"a\")
+    r: leaf(bytes=1, \"b\")
+  r: leaf(bytes=20, \"Hello, World! He…\")
+"
+    );
+    assert_eq!(Rope::empty().dump_structure(), "leaf(bytes=0, \"\")\n");
+}
+
+#[test]
+fn test_to_dot() {
+    let rope = Rope::from_leaves(vec![Rope::new("a\"b"), Rope::new("c\n")]);
+    assert_eq!(
+        rope.to_dot(),
+        r#"digraph rope {
+    node [shape=box];
+    n0 [label="weight=3\nbytes=5\nchars=5\ndepth=1"];
+    n1 [shape=ellipse, label="bytes=3\na\"b"];
+    n0 -> n1 [label=l];
+    n2 [shape=ellipse, label="bytes=2\nc\n"];
+    n0 -> n2 [label=r];
+}
+"#
+    );
+
+    // the copies of a repeated rope are one subtree
+    let repeated = Rope::new("ab").repeat(8);
+    assert_eq!(repeated.to_dot().matches("[shape=ellipse").count(), 1);
+}
@@ -0,0 +1,199 @@
+use crate::rope::Rope;
+
+/// Where `to_display_string` cuts text that is too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ellipsis {
+    /// Keep the start and the end, as for paths.
+    Middle,
+    /// Keep the start, as for messages.
+    End,
+}
+
+impl Rope {
+    /// The text cut down to at most `max_chars` chars for a log line, a
+    /// tooltip or a diagnostic, with `…` where text was left out.
+    ///
+    /// Cuts never split a grapheme cluster, so the preview has fewer chars
+    /// than `max_chars` rather than a broken glyph. The cluster rules are
+    /// applied for combining marks, variation selectors, Indic vowel signs,
+    /// Hangul jamo, flags and emoji sequences, with the chars they apply to
+    /// listed by hand rather than taken from the full Unicode tables.
+    pub fn to_display_string(&self, max_chars: usize, ellipsis: Ellipsis) -> String {
+        let len = self.len_chars();
+        if len <= max_chars {
+            return self.to_string();
+        }
+        if max_chars == 0 {
+            return String::new();
+        }
+        let budget = max_chars - 1;
+        let (head, tail) = match ellipsis {
+            Ellipsis::Middle => (budget - budget / 2, budget / 2),
+            Ellipsis::End => (budget, 0),
+        };
+
+        let mut head_end = head;
+        while head_end > 0 && self.within_cluster(head_end) {
+            head_end -= 1;
+        }
+        let mut tail_start = len - tail;
+        while tail_start < len && self.within_cluster(tail_start) {
+            tail_start += 1;
+        }
+
+        let mut preview = self.slice_to_string(..head_end);
+        preview.push('…');
+        preview.push_str(&self.slice_to_string(tail_start..));
+        preview
+    }
+
+    // whether the char at char offset `offset` belongs with the one before
+    fn within_cluster(&self, offset: usize) -> bool {
+        let mut chars = self.chars_at(offset - 1);
+        let (prev, c) = match (chars.next(), chars.next()) {
+            (Some(prev), Some(c)) => (prev, c),
+            _ => return false,
+        };
+        if is_regional(prev) && is_regional(c) {
+            // flags are pairs of indicators, counted from the first one
+            let before = self.chunks_rev_at(self.char_to_byte(offset));
+            let run = before
+                .flat_map(|chunk| chunk.chars().rev())
+                .take_while(|&c| is_regional(c))
+                .count();
+            return run % 2 == 1;
+        }
+        extends(prev, c)
+    }
+}
+
+fn extends(prev: char, c: char) -> bool {
+    let marks = matches!(c,
+        // combining marks
+        '\u{300}'..='\u{36f}'
+        | '\u{1ab0}'..='\u{1aff}'
+        | '\u{1dc0}'..='\u{1dff}'
+        | '\u{20d0}'..='\u{20ff}'
+        | '\u{fe20}'..='\u{fe2f}'
+        // variation selectors
+        | '\u{fe00}'..='\u{fe0f}'
+        | '\u{e0100}'..='\u{e01ef}'
+        // emoji skin tones and the zero width joiner
+        | '\u{1f3fb}'..='\u{1f3ff}'
+        | '\u{200d}'
+        // Thai vowel signs and tone marks
+        | '\u{e31}'
+        | '\u{e34}'..='\u{e3a}'
+        | '\u{e47}'..='\u{e4e}');
+    marks
+        || is_indic_sign(c)
+        || hangul_joins(prev, c)
+        || prev == '\u{200d}'
+        || (prev == '\r' && c == '\n')
+}
+
+fn is_regional(c: char) -> bool {
+    matches!(c, '\u{1f1e6}'..='\u{1f1ff}')
+}
+
+// the vowel signs, viramas and nasal signs of the Indic scripts from
+// Devanagari to Sinhala, whose blocks all lay them out the same way
+fn is_indic_sign(c: char) -> bool {
+    let c = u32::from(c);
+    if !(0x900..0xe00).contains(&c) {
+        return false;
+    }
+    matches!(c % 0x80, 0x01..=0x03 | 0x3c | 0x3e..=0x4d | 0x55..=0x57 | 0x62..=0x63)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Jamo {
+    Lead,
+    Vowel,
+    Trail,
+    // precomposed syllables, without and with a trailing consonant
+    Open,
+    Closed,
+}
+
+fn jamo(c: char) -> Option<Jamo> {
+    match c {
+        '\u{1100}'..='\u{115f}' | '\u{a960}'..='\u{a97c}' => Some(Jamo::Lead),
+        '\u{1160}'..='\u{11a7}' | '\u{d7b0}'..='\u{d7c6}' => Some(Jamo::Vowel),
+        '\u{11a8}'..='\u{11ff}' | '\u{d7cb}'..='\u{d7fb}' => Some(Jamo::Trail),
+        '\u{ac00}'..='\u{d7a3}' => match (u32::from(c) - 0xac00) % 28 {
+            0 => Some(Jamo::Open),
+            _ => Some(Jamo::Closed),
+        },
+        _ => None,
+    }
+}
+
+// whether the Hangul jamo and syllables `prev` and `c` make one syllable
+fn hangul_joins(prev: char, c: char) -> bool {
+    use Jamo::*;
+    match (jamo(prev), jamo(c)) {
+        (Some(Lead), Some(_)) => jamo(c) != Some(Trail),
+        (Some(Vowel), Some(next)) | (Some(Open), Some(next)) => next == Vowel || next == Trail,
+        (Some(Trail), Some(Trail)) | (Some(Closed), Some(Trail)) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn test_to_display_string() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("/home/user/"),
+        Rope::new("projects/rope/src/lib.rs"),
+    ]);
+
+    assert_eq!(rope.to_display_string(100, Ellipsis::End), rope.to_string());
+    assert_eq!(rope.to_display_string(11, Ellipsis::End), "/home/user…");
+    assert_eq!(rope.to_display_string(11, Ellipsis::Middle), "/home…ib.rs");
+    assert_eq!(rope.to_display_string(1, Ellipsis::Middle), "…");
+    assert_eq!(rope.to_display_string(0, Ellipsis::End), "");
+}
+
+#[test]
+fn test_to_display_string_clusters() {
+    // e and a combining acute accent, a family emoji joined with ZWJs
+    let rope = Rope::new("cafe\u{301} 👨\u{200d}👩\u{200d}👧 ok");
+
+    assert_eq!(rope.to_display_string(5, Ellipsis::End), "caf…");
+    assert_eq!(rope.to_display_string(6, Ellipsis::End), "cafe\u{301}…");
+    assert_eq!(rope.to_display_string(9, Ellipsis::End), "cafe\u{301} …");
+    assert_eq!(rope.to_display_string(7, Ellipsis::Middle), "caf… ok");
+    assert_eq!(rope.to_display_string(9, Ellipsis::Middle), "caf… ok");
+}
+
+#[test]
+fn test_to_display_string_scripts() {
+    // German and French flags
+    let flags = Rope::new("ab\u{1f1e9}\u{1f1ea}\u{1f1eb}\u{1f1f7}cd");
+    assert_eq!(
+        flags.to_display_string(5, Ellipsis::End),
+        "ab\u{1f1e9}\u{1f1ea}…"
+    );
+    assert_eq!(flags.to_display_string(4, Ellipsis::End), "ab…");
+    assert_eq!(
+        flags.to_display_string(6, Ellipsis::End),
+        "ab\u{1f1e9}\u{1f1ea}…"
+    );
+    assert_eq!(flags.to_display_string(5, Ellipsis::Middle), "ab…cd");
+
+    // ka with the vowel sign i
+    let indic = Rope::new("x\u{915}\u{93f}y");
+    assert_eq!(indic.to_display_string(3, Ellipsis::End), "x…");
+    assert_eq!(indic.to_display_string(3, Ellipsis::Middle), "x…y");
+    let indic = Rope::new("xy\u{915}\u{93f}");
+    assert_eq!(indic.to_display_string(3, Ellipsis::Middle), "x…");
+
+    // a syllable spelled out in jamo, then a precomposed one
+    let hangul = Rope::new("a\u{1100}\u{1161}\u{11a8}\u{d55c}\u{11a8}b");
+    assert_eq!(hangul.to_display_string(4, Ellipsis::End), "a…");
+    assert_eq!(
+        hangul.to_display_string(6, Ellipsis::End),
+        "a\u{1100}\u{1161}\u{11a8}…"
+    );
+    assert_eq!(hangul.to_display_string(3, Ellipsis::Middle), "a…b");
+}
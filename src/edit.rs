@@ -0,0 +1,354 @@
+use std::ops::Range;
+
+use crate::rope::Rope;
+
+impl Rope {
+    /// Returns a new rope with the lines in `lines` repeated right after
+    /// themselves. The copy shares its leaves with the original block, so
+    /// duplicating even huge blocks costs O(log n).
+    pub fn duplicate_lines(&self, lines: Range<usize>) -> Rope {
+        let last = self.len_lines();
+        let (first, end) = (lines.start.min(last), lines.end.min(last));
+        if first >= end {
+            return self.share();
+        }
+
+        let (start, stop) = (self.line_to_char(first), self.line_to_char(end));
+        let block = self.slice(start..stop);
+        let terminated = stop > start && self.chars_at(stop - 1).next() == Some('\n');
+        if end < last || terminated {
+            return self.insert_slice(stop, block);
+        }
+        // the last line has no line break to carry over, add one in between
+        let mut rope = self.share();
+        rope.insert(self.len(), "\n");
+        rope.insert_slice(stop + 1, block)
+    }
+
+    /// Returns a new rope with the lines in `lines` moved `delta` lines down
+    /// (or up, if negative), clamped to the document. The moved block is
+    /// extracted and spliced back in with its leaves shared. An empty or
+    /// reversed range leaves the rope as it is.
+    pub fn move_lines(&self, lines: Range<usize>, delta: isize) -> Rope {
+        if lines.start >= lines.end {
+            return self.share();
+        }
+        // lines are easiest to shuffle when each one ends with a line break
+        let len = self.len_chars();
+        let terminated = len > 0 && self.chars_at(len - 1).next() == Some('\n');
+        let mut base = self.share();
+        if !terminated {
+            base.insert(self.len(), "\n");
+        }
+
+        let last = base.len_lines() - 1;
+        let (first, end) = (lines.start.min(last), lines.end.min(last));
+        let count = end.saturating_sub(first);
+        let target = (first as isize + delta).clamp(0, (last - count) as isize) as usize;
+
+        let start = base.line_to_char(first);
+        let stop = base.line_to_char(end);
+        let block = base.slice(start..stop);
+        let rest = base
+            .slice(0..start)
+            .to_rope()
+            .insert_slice(start, base.slice(stop..base.len_chars()));
+        let moved = rest.insert_slice(rest.line_to_char(target), block);
+
+        if terminated {
+            return moved;
+        }
+        moved.slice(0..moved.len_chars() - 1).to_rope()
+    }
+
+    /// Returns a new rope with the lines in `lines` joined into one, each
+    /// interior line break replaced by `separator`. With `trim_indent` the
+    /// leading whitespace of the joined lines is dropped as well.
+    pub fn join_lines(&self, lines: Range<usize>, separator: &str, trim_indent: bool) -> Rope {
+        let end = lines.end.min(self.len_lines());
+        if lines.start + 1 >= end {
+            return self.share();
+        }
+        let start = self.line_to_char(lines.start);
+        let stop = self.line_to_char(end - 1) + self.line_chars(end - 1).count();
+
+        let mut joined = String::new();
+        let mut trimming = false;
+        for c in self.chars_at(start).take(stop - start) {
+            if c == '\n' {
+                joined.push_str(separator);
+                trimming = trim_indent;
+                continue;
+            }
+            if trimming && (c == ' ' || c == '\t') {
+                continue;
+            }
+            trimming = false;
+            joined.push(c);
+        }
+        self.replace_with(start..stop, Rope::chunked(&joined))
+    }
+
+    /// Returns a new rope with the lines in `lines` padded with spaces so
+    /// that every occurrence of `delimiter` lines up in columns, as when
+    /// aligning the `=` of assignments or the `|` of a table. Lines without
+    /// the delimiter are left alone.
+    pub fn align_columns(&self, lines: Range<usize>, delimiter: &str) -> Rope {
+        let end = lines.end.min(self.len_lines());
+        if delimiter.is_empty() || lines.start >= end {
+            return self.share();
+        }
+
+        // byte offset of each line and the char width of its fields
+        let mut rows = Vec::new();
+        let mut widths: Vec<usize> = Vec::new();
+        for line in lines.start..end {
+            let text: String = self.line_chars(line).collect();
+            let fields: Vec<usize> = text.split(delimiter).map(|f| f.chars().count()).collect();
+            for (column, &width) in fields[..fields.len() - 1].iter().enumerate() {
+                match widths.get_mut(column) {
+                    Some(max) => *max = (*max).max(width),
+                    None => widths.push(width),
+                }
+            }
+            let at = self.char_to_byte(self.line_to_char(line));
+            rows.push((at, text, fields));
+        }
+
+        // pad from the end backwards so pending offsets stay valid
+        let mut rope = self.share();
+        for (at, text, fields) in rows.iter().rev() {
+            let delimiters = text.match_indices(delimiter).map(|(i, _)| i);
+            let pads: Vec<(usize, usize)> = delimiters
+                .zip(fields.iter().zip(&widths))
+                .map(|(i, (width, max))| (at + i, max - width))
+                .collect();
+            for &(offset, pad) in pads.iter().rev().filter(|(_, pad)| *pad > 0) {
+                rope.insert(offset, &" ".repeat(pad));
+            }
+        }
+        rope
+    }
+
+    /// Returns a new rope with the char before `offset` swapped with the one
+    /// at `offset`, or the two chars before `offset` at the end of a line.
+    pub fn transpose_chars(&self, offset: usize) -> Rope {
+        match self.char_pair(offset) {
+            Some(at) => {
+                let mut pair = self.chars_at(at);
+                let (a, b) = (pair.next().unwrap(), pair.next().unwrap());
+                self.replace_with(at..at + 2, Rope::new(&format!("{}{}", b, a)))
+            }
+            None => self.share(),
+        }
+    }
+
+    /// Returns a new rope with the word at or before `offset` swapped with
+    /// the word following it. Separators between the words stay in place.
+    pub fn transpose_words(&self, offset: usize) -> Rope {
+        match self.word_pair(offset) {
+            Some((first, second)) => {
+                let mut swapped: String = self.chars_at(second.start).take(second.len()).collect();
+                swapped.extend(self.chars_at(first.end).take(second.start - first.end));
+                swapped.extend(self.chars_at(first.start).take(first.len()));
+                self.replace_with(first.start..second.end, Rope::chunked(&swapped))
+            }
+            None => self.share(),
+        }
+    }
+
+    // offset of the first of the two chars transposed around `offset`
+    pub(crate) fn char_pair(&self, offset: usize) -> Option<usize> {
+        let len = self.len_chars();
+        if len < 2 || offset == 0 {
+            return None;
+        }
+        let at_eol = offset >= len || self.chars_at(offset).next() == Some('\n');
+        if at_eol {
+            // fewer than two chars before a line break leave nothing to swap
+            return offset.min(len).checked_sub(2);
+        }
+        Some(offset - 1)
+    }
+
+    // the words transposed around `offset`
+    pub(crate) fn word_pair(&self, offset: usize) -> Option<(Range<usize>, Range<usize>)> {
+        let len = self.len_chars();
+        let is_word = |i: usize| {
+            self.chars_at(i)
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        };
+
+        // step out of the word under the cursor
+        let mut pos = offset.min(len);
+        while pos < len && is_word(pos) {
+            pos += 1;
+        }
+
+        let mut first_end = pos;
+        while first_end > 0 && !is_word(first_end - 1) {
+            first_end -= 1;
+        }
+        let mut first_start = first_end;
+        while first_start > 0 && is_word(first_start - 1) {
+            first_start -= 1;
+        }
+
+        let mut second_start = pos;
+        while second_start < len && !is_word(second_start) {
+            second_start += 1;
+        }
+        let mut second_end = second_start;
+        while second_end < len && is_word(second_end) {
+            second_end += 1;
+        }
+
+        if first_start == first_end || second_start == second_end {
+            return None;
+        }
+        Some((first_start..first_end, second_start..second_end))
+    }
+
+    // new rope with the chars in `range` swapped for `rope`
+    fn replace_with(&self, range: Range<usize>, rope: Rope) -> Rope {
+        let head = self.slice(0..range.start).to_rope();
+        let tail = self.slice(range.end..self.len_chars()).to_rope();
+        Rope::concat(Rope::concat(head, rope), tail)
+    }
+}
+
+#[test]
+fn test_duplicate_lines() {
+    let rope = Rope::new("one\ntwo\nthree");
+
+    let dup = rope.duplicate_lines(0..2);
+    assert_eq!(dup.chars().collect::<String>(), "one\ntwo\none\ntwo\nthree");
+
+    let dup = rope.duplicate_lines(1..3);
+    assert_eq!(
+        dup.chars().collect::<String>(),
+        "one\ntwo\nthree\ntwo\nthree"
+    );
+
+    let dup = rope.duplicate_lines(1..1);
+    assert_eq!(dup.chars().collect::<String>(), "one\ntwo\nthree");
+
+    // a block already ending in a line break needs none added
+    let dup = Rope::new("a\nb\n").duplicate_lines(0..3);
+    assert_eq!(dup.chars().collect::<String>(), "a\nb\na\nb\n");
+    let dup = Rope::new("a\n").duplicate_lines(1..2);
+    assert_eq!(dup.chars().collect::<String>(), "a\n\n");
+}
+
+#[test]
+fn test_move_lines() {
+    let rope = Rope::new("one\ntwo\nthree\nfour");
+
+    let moved = rope.move_lines(0..1, 2);
+    assert_eq!(moved.chars().collect::<String>(), "two\nthree\none\nfour");
+
+    let moved = rope.move_lines(2..4, -1);
+    assert_eq!(moved.chars().collect::<String>(), "one\nthree\nfour\ntwo");
+
+    let moved = rope.move_lines(1..2, 10);
+    assert_eq!(moved.chars().collect::<String>(), "one\nthree\nfour\ntwo");
+
+    let moved = rope.move_lines(1..3, -10);
+    assert_eq!(moved.chars().collect::<String>(), "two\nthree\none\nfour");
+
+    let moved = rope.move_lines(Range { start: 3, end: 1 }, 1);
+    assert_eq!(moved.chars().collect::<String>(), "one\ntwo\nthree\nfour");
+}
+
+#[test]
+fn test_join_lines() {
+    let rope = Rope::new("call(\n    a,\n    b\n)\nnext");
+
+    let joined = rope.join_lines(0..4, "", true);
+    assert_eq!(joined.chars().collect::<String>(), "call(a,b)\nnext");
+
+    let joined = rope.join_lines(1..3, " ", false);
+    assert_eq!(
+        joined.chars().collect::<String>(),
+        "call(\n    a,     b\n)\nnext"
+    );
+
+    let joined = rope.join_lines(4..5, " ", true);
+    assert_eq!(
+        joined.chars().collect::<String>(),
+        "call(\n    a,\n    b\n)\nnext"
+    );
+}
+
+#[test]
+fn test_align_columns() {
+    let rope = Rope::new(
+        "a = 1
+long = 2
+skip
+mid = 3
+",
+    );
+    assert_eq!(
+        rope.align_columns(0..4, "=").chars().collect::<String>(),
+        "a    = 1\nlong = 2\nskip\nmid  = 3\n"
+    );
+
+    let table = Rope::new("|x|yy|\n|long|z|\n");
+    assert_eq!(
+        table.align_columns(0..2, "|").chars().collect::<String>(),
+        "|x   |yy|\n|long|z |\n"
+    );
+    assert_eq!(
+        table.align_columns(1..2, "|").chars().collect::<String>(),
+        "|x|yy|\n|long|z|\n"
+    );
+}
+
+#[test]
+fn test_transpose_chars() {
+    let rope = Rope::new("abcd\nef");
+
+    assert_eq!(
+        rope.transpose_chars(1).chars().collect::<String>(),
+        "bacd\nef"
+    );
+    assert_eq!(
+        rope.transpose_chars(4).chars().collect::<String>(),
+        "abdc\nef"
+    );
+    assert_eq!(
+        rope.transpose_chars(7).chars().collect::<String>(),
+        "abcd\nfe"
+    );
+    assert_eq!(
+        rope.transpose_chars(0).chars().collect::<String>(),
+        "abcd\nef"
+    );
+
+    let rope = Rope::new("a\nb");
+    assert_eq!(rope.transpose_chars(1).chars().collect::<String>(), "a\nb");
+}
+
+#[test]
+fn test_transpose_words() {
+    let rope = Rope::new("one, two three");
+
+    assert_eq!(
+        rope.transpose_words(4).chars().collect::<String>(),
+        "two, one three"
+    );
+    assert_eq!(
+        rope.transpose_words(1).chars().collect::<String>(),
+        "two, one three"
+    );
+    assert_eq!(
+        rope.transpose_words(8).chars().collect::<String>(),
+        "one, three two"
+    );
+    assert_eq!(
+        rope.transpose_words(14).chars().collect::<String>(),
+        "one, two three"
+    );
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+use std::ops::{Bound, Range, RangeBounds};
+
+use crate::rope::Rope;
+use crate::slice::RopeSlice;
+
+/// Why an offset or range was rejected by one of the `try_` methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RopeError {
+    /// The offset is past the end of the rope, which is `len` long.
+    OutOfBounds { offset: usize, len: usize },
+    /// The byte offset falls inside a multi-byte char.
+    NotCharBoundary { offset: usize },
+    /// The range starts after it ends.
+    InvalidRange { start: usize, end: usize },
+    /// The edit would make the rope `len` bytes long, past `Rope::MAX_LEN`.
+    TooLong { len: usize },
+    /// The edit touches the read-only region `start..end`.
+    Protected { start: usize, end: usize },
+}
+
+impl fmt::Display for RopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RopeError::OutOfBounds { offset, len } => {
+                write!(f, "offset {} out of bounds for length {}", offset, len)
+            }
+            RopeError::NotCharBoundary { offset } => {
+                write!(f, "byte offset {} is not a char boundary", offset)
+            }
+            RopeError::InvalidRange { start, end } => {
+                write!(f, "range starts at {} but ends at {}", start, end)
+            }
+            RopeError::TooLong { len } => {
+                write!(f, "length {} exceeds the maximum of {}", len, Rope::MAX_LEN)
+            }
+            RopeError::Protected { start, end } => {
+                write!(f, "bytes {}..{} are read-only", start, end)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RopeError {}
+
+impl Rope {
+    /// The longest a rope can get, in bytes: the same limit as for a
+    /// `String`. Ropes sharing subtrees, such as those made by `repeat`, can
+    /// reach it without holding that much memory, which is quickly done on
+    /// 32-bit targets like wasm32. Plain edits such as `insert`, `push_str`
+    /// or `+` panic past it; the `try_` methods report it instead.
+    pub const MAX_LEN: usize = isize::MAX as usize;
+
+    /// Like `insert`, but rejects offsets past the end or inside a char, and
+    /// text making the rope longer than `MAX_LEN`, instead of panicking.
+    pub fn try_insert(&mut self, offset: usize, s: &str) -> Result<(), RopeError> {
+        self.check_byte(offset)?;
+        self.check_grow(s.len())?;
+        self.insert(offset, s);
+        Ok(())
+    }
+
+    /// Like `append`, but rejects ropes making self longer than `MAX_LEN`.
+    pub fn try_append(&mut self, other: Rope) -> Result<(), RopeError> {
+        self.check_grow(other.len())?;
+        self.append(other);
+        Ok(())
+    }
+
+    /// Like `repeat`, but rejects counts making the rope longer than
+    /// `MAX_LEN` instead of panicking.
+    pub fn try_repeat(&self, n: usize) -> Result<Rope, RopeError> {
+        match self.len().checked_mul(n) {
+            Some(len) if len <= Rope::MAX_LEN => Ok(self.repeat(n)),
+            len => Err(RopeError::TooLong {
+                len: len.unwrap_or(usize::MAX),
+            }),
+        }
+    }
+
+    /// Like `remove`, but rejects bad ranges instead of panicking.
+    pub fn try_remove<R: RangeBounds<usize>>(&mut self, range: R) -> Result<(), RopeError> {
+        let range = self.try_byte_range(range)?;
+        self.remove(range);
+        Ok(())
+    }
+
+    /// Like `slice`, but rejects char ranges reaching past the end instead
+    /// of clamping them.
+    pub fn try_slice(&self, range: Range<usize>) -> Result<RopeSlice<'_>, RopeError> {
+        if range.start > range.end {
+            return Err(RopeError::InvalidRange {
+                start: range.start,
+                end: range.end,
+            });
+        }
+        let len = self.len_chars();
+        if range.end > len {
+            return Err(RopeError::OutOfBounds {
+                offset: range.end,
+                len,
+            });
+        }
+        Ok(self.slice(range))
+    }
+
+    /// The char at char offset `i`.
+    pub fn try_index(&self, i: usize) -> Result<char, RopeError> {
+        let len = self.len_chars();
+        if i >= len {
+            return Err(RopeError::OutOfBounds { offset: i, len });
+        }
+        Ok(self.chars_at(i).next().expect("offset within the rope"))
+    }
+
+    /// Turns a byte range reported by an external tool, such as a compiler
+    /// or grep, into a char range of the rope. Ends past the end of the rope
+    /// are clamped to it; ends inside a char are rejected.
+    pub fn sanitize_range(&self, range: Range<usize>) -> Result<Range<usize>, RopeError> {
+        if range.start > range.end {
+            return Err(RopeError::InvalidRange {
+                start: range.start,
+                end: range.end,
+            });
+        }
+        let start = range.start.min(self.len());
+        let end = range.end.min(self.len());
+        self.check_byte(start)?;
+        self.check_byte(end)?;
+        Ok(self.byte_to_char(start)..self.byte_to_char(end))
+    }
+
+    /// Resolves a byte range against the rope, checking both ends.
+    pub(crate) fn try_byte_range<R: RangeBounds<usize>>(
+        &self,
+        range: R,
+    ) -> Result<Range<usize>, RopeError> {
+        // the offset after `n`, which can't exist past usize::MAX
+        let after = |n: usize| {
+            n.checked_add(1).ok_or(RopeError::OutOfBounds {
+                offset: n,
+                len: self.len(),
+            })
+        };
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => after(n)?,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => after(n)?,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len(),
+        };
+        if start > end {
+            return Err(RopeError::InvalidRange { start, end });
+        }
+        self.check_byte(start)?;
+        self.check_byte(end)?;
+        Ok(start..end)
+    }
+
+    fn check_grow(&self, added: usize) -> Result<(), RopeError> {
+        match self.len().checked_add(added) {
+            Some(len) if len <= Rope::MAX_LEN => Ok(()),
+            len => Err(RopeError::TooLong {
+                len: len.unwrap_or(usize::MAX),
+            }),
+        }
+    }
+
+    fn check_byte(&self, offset: usize) -> Result<(), RopeError> {
+        if offset > self.len() {
+            return Err(RopeError::OutOfBounds {
+                offset,
+                len: self.len(),
+            });
+        }
+        if !self.is_char_boundary(offset) {
+            return Err(RopeError::NotCharBoundary { offset });
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_try_edits() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+
+    assert_eq!(
+        rope.try_insert(3, "x"),
+        Err(RopeError::NotCharBoundary { offset: 3 })
+    );
+    assert_eq!(
+        rope.try_insert(16, "x"),
+        Err(RopeError::OutOfBounds {
+            offset: 16,
+            len: 15
+        })
+    );
+    assert_eq!(
+        rope.try_remove((Bound::Included(5), Bound::Excluded(3))),
+        Err(RopeError::InvalidRange { start: 5, end: 3 })
+    );
+    assert!(rope.try_remove(2..=2).is_err());
+    assert_eq!(
+        rope.try_remove(..=usize::MAX),
+        Err(RopeError::OutOfBounds {
+            offset: usize::MAX,
+            len: 15
+        })
+    );
+    assert!(rope
+        .try_remove((Bound::Excluded(usize::MAX), Bound::Unbounded))
+        .is_err());
+    assert_eq!(rope.chars().collect::<String>(), "Grüße, World!");
+
+    rope.try_insert(15, "?").unwrap();
+    rope.try_remove(2..4).unwrap();
+    assert_eq!(rope.chars().collect::<String>(), "Grße, World!?");
+}
+
+#[test]
+fn test_sanitize_range() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+
+    assert_eq!(rope.sanitize_range(2..6), Ok(2..4));
+    assert_eq!(rope.sanitize_range(9..100), Ok(7..13));
+    assert_eq!(rope.sanitize_range(50..100), Ok(13..13));
+    assert_eq!(
+        rope.sanitize_range(3..6),
+        Err(RopeError::NotCharBoundary { offset: 3 })
+    );
+    // tools do report reversed ranges
+    let (start, end) = (6, 2);
+    assert_eq!(
+        rope.sanitize_range(start..end),
+        Err(RopeError::InvalidRange { start: 6, end: 2 })
+    );
+}
+
+#[test]
+fn test_try_slice_and_index() {
+    let rope = Rope::new("Grüße");
+
+    assert_eq!(rope.try_slice(1..3).unwrap(), "rü");
+    assert!(rope.try_slice(0..5).is_ok());
+    assert_eq!(
+        rope.try_slice(2..6),
+        Err(RopeError::OutOfBounds { offset: 6, len: 5 })
+    );
+    assert_eq!(rope.try_index(3), Ok('ß'));
+    assert_eq!(
+        rope.try_index(5),
+        Err(RopeError::OutOfBounds { offset: 5, len: 5 })
+    );
+    assert_eq!(
+        RopeError::NotCharBoundary { offset: 3 }.to_string(),
+        "byte offset 3 is not a char boundary"
+    );
+}
+
+#[test]
+fn test_max_len() {
+    // counts are cached, so a rope this long only holds one copy of the text
+    let huge = Rope::new("ab").try_repeat(Rope::MAX_LEN / 2).unwrap();
+    assert_eq!(huge.len(), Rope::MAX_LEN - Rope::MAX_LEN % 2);
+    assert_eq!(huge.len_chars(), huge.len());
+    assert_eq!(huge.byte_to_char(huge.len()), huge.len_chars());
+
+    let mut grown = huge.clone();
+    assert_eq!(
+        grown.try_insert(0, "abc"),
+        Err(RopeError::TooLong {
+            len: huge.len() + 3
+        })
+    );
+    assert_eq!(
+        grown.try_append(huge.clone()),
+        Err(RopeError::TooLong {
+            len: huge.len() * 2
+        })
+    );
+    assert_eq!(grown.len(), huge.len());
+
+    assert_eq!(
+        Rope::new("abc").try_repeat(Rope::MAX_LEN / 2),
+        Err(RopeError::TooLong {
+            len: (Rope::MAX_LEN / 2) * 3
+        })
+    );
+    assert!(Rope::new("abc").try_repeat(usize::MAX).is_err());
+    assert_eq!(Rope::empty().try_repeat(usize::MAX), Ok(Rope::empty()));
+}
+
+#[test]
+#[should_panic(expected = "rope too long")]
+fn test_max_len_plain_edits() {
+    let mut huge = Rope::new("ab").repeat(Rope::MAX_LEN / 2);
+    huge += "abc";
+}
@@ -0,0 +1,135 @@
+use std::ops::BitOr;
+
+use crate::rope::Rope;
+
+/// Content predicates cached for every subtree and updated on edit, so
+/// questions like "does this file contain tabs?" are answered in O(1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags(u8);
+
+impl Flags {
+    pub const TAB: Flags = Flags(1);
+    pub const CRLF: Flags = Flags(1 << 1);
+    pub const NON_ASCII: Flags = Flags(1 << 2);
+    pub const CONTROL: Flags = Flags(1 << 3);
+    // a space or tab right before a line break or at the end of the text
+    pub(crate) const TRAILING_WS: Flags = Flags(1 << 4);
+    // needed to spot a CRLF split across two leaves
+    pub(crate) const STARTS_LF: Flags = Flags(1 << 5);
+    pub(crate) const ENDS_CR: Flags = Flags(1 << 6);
+
+    pub(crate) fn of(s: &str) -> Flags {
+        let mut flags = Flags::default();
+        let mut prev = None;
+        for b in s.bytes() {
+            flags = flags
+                | match b {
+                    b'\t' => Flags::TAB,
+                    b'\n' if prev == Some(b'\r') => Flags::CRLF,
+                    b'\n' if matches!(prev, Some(b' ') | Some(b'\t')) => Flags::TRAILING_WS,
+                    b'\n' | b'\r' => Flags::default(),
+                    0..=0x1f | 0x7f => Flags::CONTROL,
+                    0x80..=0xff => Flags::NON_ASCII,
+                    _ => Flags::default(),
+                };
+            prev = Some(b);
+        }
+        if s.ends_with([' ', '\t']) {
+            flags = flags | Flags::TRAILING_WS;
+        }
+        if s.starts_with('\n') {
+            flags = flags | Flags::STARTS_LF;
+        }
+        if s.ends_with('\r') {
+            flags = flags | Flags::ENDS_CR;
+        }
+        flags
+    }
+
+    // flags of the concatenation of two non-empty texts
+    pub(crate) fn join(left: Flags, right: Flags) -> Flags {
+        let edges = Flags::STARTS_LF.0 | Flags::ENDS_CR.0;
+        let mut flags = Flags((left.0 | right.0) & !edges);
+        if left.contains(Flags::ENDS_CR) && right.contains(Flags::STARTS_LF) {
+            flags = flags | Flags::CRLF;
+        }
+        Flags(flags.0 | (left.0 & Flags::STARTS_LF.0) | (right.0 & Flags::ENDS_CR.0))
+    }
+
+    pub(crate) fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub(crate) fn from_bits(bits: u8) -> Flags {
+        Flags(bits)
+    }
+
+    pub fn contains(self, other: Flags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Flags {
+    type Output = Flags;
+
+    fn bitor(self, other: Flags) -> Flags {
+        Flags(self.0 | other.0)
+    }
+}
+
+impl Rope {
+    /// Cached content predicates of the whole rope.
+    pub fn flags(&self) -> Flags {
+        match self {
+            Rope::Leaf(leaf) => leaf.flags,
+            Rope::Node(node) => node.flags,
+        }
+    }
+
+    pub fn has_tabs(&self) -> bool {
+        self.flags().contains(Flags::TAB)
+    }
+
+    pub fn has_crlf(&self) -> bool {
+        self.flags().contains(Flags::CRLF)
+    }
+
+    /// Whether the rope holds control chars other than tabs and line breaks.
+    pub fn has_control_chars(&self) -> bool {
+        self.flags().contains(Flags::CONTROL)
+    }
+
+    /// Whether the rope holds only ASCII text.
+    pub fn is_ascii(&self) -> bool {
+        !self.flags().contains(Flags::NON_ASCII)
+    }
+
+    // whether the subtree may hold a line with trailing whitespace
+    pub(crate) fn trailing_ws(&self) -> bool {
+        self.flags().contains(Flags::TRAILING_WS)
+    }
+}
+
+#[test]
+fn test_flags() {
+    let rope = Rope::from_leaves(vec![Rope::new("fn main() {\n"), Rope::new("\tok();\n}")]);
+
+    assert!(rope.has_tabs());
+    assert!(!rope.has_crlf());
+    assert!(!rope.has_control_chars());
+    assert!(rope.is_ascii());
+
+    let mut rope = rope;
+    rope.insert(3, "\u{7}é");
+    assert!(rope.has_control_chars());
+    assert!(!rope.is_ascii());
+}
+
+#[test]
+fn test_flags_crlf_across_leaves() {
+    let rope = Rope::from_leaves(vec![Rope::new("a\r"), Rope::new("\nb")]);
+    assert!(rope.has_crlf());
+
+    let rope = Rope::from_leaves(vec![Rope::new("a\r"), Rope::new("b\n")]);
+    assert!(!rope.has_crlf());
+}
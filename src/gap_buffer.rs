@@ -0,0 +1,214 @@
+use std::ops::Range;
+
+use crate::buffer::TextBuffer;
+use crate::builder::RopeBuilder;
+use crate::rope::Rope;
+
+/// Size in bytes past which a `GapBuffer` promotes itself to a rope.
+pub const PROMOTE_LEN: usize = 1 << 20;
+
+const MIN_GAP: usize = 64;
+
+// a plain gap buffer over chars: text lives in buf[..start] and buf[end..],
+// buf[start..end] is free space that follows the last edit position
+#[derive(Debug, Default)]
+pub(crate) struct Gap {
+    buf: Vec<char>,
+    start: usize,
+    end: usize,
+    bytes: usize,
+}
+
+impl Gap {
+    pub(crate) fn new(s: &str) -> Gap {
+        let mut gap = Gap::default();
+        gap.insert(0, s);
+        gap
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.buf.len() - (self.end - self.start)
+    }
+
+    pub(crate) fn len_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub(crate) fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.buf[..self.start]
+            .iter()
+            .chain(self.buf[self.end..].iter())
+            .copied()
+    }
+
+    fn move_gap(&mut self, at: usize) {
+        if at < self.start {
+            let n = self.start - at;
+            self.buf.copy_within(at..self.start, self.end - n);
+            self.start -= n;
+            self.end -= n;
+        } else if at > self.start {
+            let n = at - self.start;
+            self.buf.copy_within(self.end..self.end + n, self.start);
+            self.start += n;
+            self.end += n;
+        }
+    }
+
+    fn reserve(&mut self, n: usize) {
+        if self.end - self.start >= n {
+            return;
+        }
+        let grow = n.max(MIN_GAP).max(self.buf.len() / 2);
+        let tail = self.buf.len() - self.end;
+        self.buf
+            .splice(self.end..self.end, std::iter::repeat_n('\0', grow));
+        self.end = self.buf.len() - tail;
+    }
+
+    pub(crate) fn insert(&mut self, at: usize, s: &str) {
+        assert!(at <= self.len(), "offset out of bounds");
+        let n = s.chars().count();
+        self.reserve(n);
+        self.move_gap(at);
+        for (i, c) in s.chars().enumerate() {
+            self.buf[self.start + i] = c;
+        }
+        self.start += n;
+        self.bytes += s.len();
+    }
+
+    pub(crate) fn remove(&mut self, range: Range<usize>) {
+        assert!(range.end <= self.len(), "range out of bounds");
+        if range.start >= range.end {
+            return;
+        }
+        self.move_gap(range.start);
+        let removed = &self.buf[self.end..self.end + range.len()];
+        self.bytes -= removed.iter().map(|c| c.len_utf8()).sum::<usize>();
+        self.end += range.len();
+    }
+
+    pub(crate) fn to_rope(&self) -> Rope {
+        let mut builder = RopeBuilder::new();
+        let mut chunk = String::new();
+        for c in self.chars() {
+            chunk.push(c);
+            if chunk.len() >= MIN_GAP {
+                builder.append(&chunk);
+                chunk.clear();
+            }
+        }
+        builder.append(&chunk);
+        builder.finish()
+    }
+}
+
+#[derive(Debug)]
+enum Storage {
+    Gap(Gap),
+    Rope(Rope),
+}
+
+/// A gap buffer for small documents. Edits close to each other only move
+/// the gap around, which is cheaper than splitting a rope. Once the text
+/// grows past `PROMOTE_LEN` bytes the buffer transparently switches to a
+/// chunked `Rope`.
+#[derive(Debug)]
+pub struct GapBuffer {
+    storage: Storage,
+}
+
+impl GapBuffer {
+    pub fn new(s: &str) -> GapBuffer {
+        let mut buffer = GapBuffer {
+            storage: Storage::Gap(Gap::new(s)),
+        };
+        buffer.promote_if_needed();
+        buffer
+    }
+
+    /// Whether the buffer has outgrown the gap and switched to a rope.
+    pub fn is_promoted(&self) -> bool {
+        matches!(self.storage, Storage::Rope(_))
+    }
+
+    pub fn into_rope(self) -> Rope {
+        match self.storage {
+            Storage::Gap(gap) => gap.to_rope(),
+            Storage::Rope(rope) => rope,
+        }
+    }
+
+    fn promote_if_needed(&mut self) {
+        if let Storage::Gap(gap) = &self.storage {
+            if gap.len_bytes() > PROMOTE_LEN {
+                self.storage = Storage::Rope(gap.to_rope());
+            }
+        }
+    }
+}
+
+impl TextBuffer for GapBuffer {
+    fn len_chars(&self) -> usize {
+        match &self.storage {
+            Storage::Gap(gap) => gap.len(),
+            Storage::Rope(rope) => rope.len_chars(),
+        }
+    }
+
+    fn insert_str(&mut self, offset: usize, s: &str) {
+        match &mut self.storage {
+            Storage::Gap(gap) => gap.insert(offset, s),
+            Storage::Rope(rope) => rope.insert_str(offset, s),
+        }
+        self.promote_if_needed();
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) {
+        match &mut self.storage {
+            Storage::Gap(gap) => gap.remove(range),
+            Storage::Rope(rope) => rope.remove_range(range),
+        }
+    }
+
+    fn slice_to_string(&self, range: Range<usize>) -> String {
+        match &self.storage {
+            Storage::Gap(gap) => gap
+                .chars()
+                .skip(range.start)
+                .take(range.end.saturating_sub(range.start))
+                .collect(),
+            Storage::Rope(rope) => TextBuffer::slice_to_string(rope, range),
+        }
+    }
+}
+
+#[test]
+fn test_gap_buffer_edits() {
+    let mut buffer = GapBuffer::new("Hello, World!");
+    buffer.insert_str(6, " Cruel");
+    buffer.remove_range(0..1);
+    buffer.insert_str(0, "J");
+    buffer.insert_str(19, "?");
+
+    assert_eq!(buffer.contents(), "Jello, Cruel World!?");
+    assert_eq!(buffer.slice_to_string(7..12), "Cruel");
+    assert!(!buffer.is_promoted());
+}
+
+#[test]
+fn test_gap_buffer_promotes() {
+    let mut buffer = GapBuffer::new("header\n");
+    let line = "x".repeat(1023) + "\n";
+    for _ in 0..1024 {
+        let len = buffer.len_chars();
+        buffer.insert_str(len, &line);
+    }
+    assert!(buffer.is_promoted());
+    assert_eq!(buffer.len_chars(), 7 + 1024 * 1024);
+
+    let rope = buffer.into_rope();
+    assert!(rope.chunks().count() > 1);
+    assert!(rope.chars().take(7).eq("header\n".chars()));
+}
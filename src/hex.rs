@@ -0,0 +1,96 @@
+use std::fmt::Write;
+
+use crate::rope::{Chunks, Rope};
+
+impl Rope {
+    /// Rows of a hex dump of the bytes from byte offset `offset` on, `width`
+    /// bytes to a row, formatted like `hexdump -C`. Rows are formatted as
+    /// they are pulled, so a viewer only pays for the rows it shows.
+    ///
+    /// The rope always holds UTF-8, so this dumps the encoded text.
+    pub fn hex_rows(&self, offset: usize, width: usize) -> HexRows<'_> {
+        let offset = offset.min(self.len());
+        let (chunks, skip) = self.chunks_at_byte(offset);
+        HexRows {
+            chunks,
+            chunk: &[],
+            skip,
+            offset,
+            width: width.max(1),
+        }
+    }
+}
+
+pub struct HexRows<'a> {
+    chunks: Chunks<'a>,
+    // rest of the current chunk
+    chunk: &'a [u8],
+    // bytes to skip at the start of the next chunk
+    skip: usize,
+    offset: usize,
+    width: usize,
+}
+
+impl<'a> HexRows<'a> {
+    fn next_byte(&mut self) -> Option<u8> {
+        while self.chunk.is_empty() {
+            self.chunk = &self.chunks.next()?.as_bytes()[self.skip..];
+            self.skip = 0;
+        }
+        let b = self.chunk[0];
+        self.chunk = &self.chunk[1..];
+        Some(b)
+    }
+}
+
+impl<'a> Iterator for HexRows<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = Vec::with_capacity(self.width);
+        while bytes.len() < self.width {
+            match self.next_byte() {
+                Some(b) => bytes.push(b),
+                None => break,
+            }
+        }
+        if bytes.is_empty() {
+            return None;
+        }
+
+        let mut row = format!("{:08x} ", self.offset);
+        for i in 0..self.width {
+            match bytes.get(i) {
+                Some(b) => write!(row, " {:02x}", b).expect("writing to a string"),
+                None => row.push_str("   "),
+            }
+        }
+        row.push_str("  |");
+        row.extend(bytes.iter().map(|&b| match b {
+            0x20..=0x7e => b as char,
+            _ => '.',
+        }));
+        row.push('|');
+
+        self.offset += bytes.len();
+        Some(row)
+    }
+}
+
+#[test]
+fn test_hex_rows() {
+    let rope = Rope::from_leaves(vec![Rope::new("Hello, "), Rope::new("Wörld!\n")]);
+
+    let rows: Vec<String> = rope.hex_rows(0, 8).collect();
+    assert_eq!(
+        rows,
+        vec![
+            "00000000  48 65 6c 6c 6f 2c 20 57  |Hello, W|",
+            "00000008  c3 b6 72 6c 64 21 0a     |..rld!.|",
+        ]
+    );
+
+    let rows: Vec<String> = rope.hex_rows(12, 4).collect();
+    assert_eq!(rows, vec!["0000000c  64 21 0a     |d!.|"]);
+    assert_eq!(rope.hex_rows(15, 4).next(), None);
+}
@@ -0,0 +1,274 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::sync::Arc;
+
+use crate::rope::Rope;
+
+/// Undo history over versions of a rope. Every version shares its untouched
+/// subtrees with its neighbours, so keeping one around costs about the size
+/// of the edit that made it.
+#[derive(Debug)]
+pub struct History {
+    // oldest first
+    pub(crate) undo: VecDeque<Rope>,
+    pub(crate) current: Rope,
+    pub(crate) redo: Vec<Rope>,
+    pub(crate) budget: Option<usize>,
+    usage: Usage,
+}
+
+impl History {
+    pub fn new(rope: Rope) -> History {
+        History::from_parts(VecDeque::new(), rope, Vec::new(), None)
+    }
+
+    pub(crate) fn from_parts(
+        undo: VecDeque<Rope>,
+        current: Rope,
+        redo: Vec<Rope>,
+        budget: Option<usize>,
+    ) -> History {
+        let mut usage = Usage::default();
+        for rope in undo.iter().chain(Some(&current)).chain(&redo) {
+            usage.add(rope);
+        }
+        History {
+            undo,
+            current,
+            redo,
+            budget,
+            usage,
+        }
+    }
+
+    pub fn current(&self) -> &Rope {
+        &self.current
+    }
+
+    /// Makes `rope` the current version, dropping the redo states.
+    pub fn record(&mut self, rope: Rope) {
+        self.usage.add(&rope);
+        let previous = mem::replace(&mut self.current, rope);
+        self.undo.push_back(previous);
+        for rope in self.redo.drain(..) {
+            self.usage.remove(&rope);
+        }
+        self.enforce_budget();
+    }
+
+    /// Applies `edit` to a copy of the current version and records the result.
+    pub fn edit<F: FnOnce(&mut Rope)>(&mut self, edit: F) {
+        let mut rope = self.current.share();
+        edit(&mut rope);
+        self.record(rope);
+    }
+
+    /// Steps back one version, returning false if there is none.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop_back() {
+            Some(rope) => {
+                self.redo.push(mem::replace(&mut self.current, rope));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps forward one undone version, returning false if there is none.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(rope) => {
+                self.undo.push_back(mem::replace(&mut self.current, rope));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Caps the memory retained by the history at about `bytes`. Past it the
+    /// oldest undo states are dropped, so the oldest one left becomes the
+    /// checkpoint undo stops at. The current version is always kept.
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        self.budget = Some(bytes);
+        self.enforce_budget();
+    }
+
+    /// Bytes retained by all the versions together. Subtrees and buffers
+    /// shared between versions are only counted once. Kept up to date as
+    /// versions come and go, so this is O(1).
+    pub fn memory_usage(&self) -> usize {
+        self.usage.total
+    }
+
+    fn enforce_budget(&mut self) {
+        if let Some(budget) = self.budget {
+            while self.usage.total > budget {
+                match self.undo.pop_front() {
+                    Some(rope) => self.usage.remove(&rope),
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+// bytes retained by a set of versions, with the number of references from
+// within the set to each node and buffer below their roots. Adding or
+// removing a version only walks the nodes it doesn't share with the others.
+// Nodes are keyed by address, which stays theirs while they are counted.
+#[derive(Debug, Default)]
+struct Usage {
+    total: usize,
+    nodes: HashMap<usize, usize>,
+    bufs: HashMap<usize, usize>,
+}
+
+impl Usage {
+    fn add(&mut self, rope: &Rope) {
+        self.total += mem::size_of::<Rope>();
+        let mut stack = vec![rope];
+        while let Some(rope) = stack.pop() {
+            match rope {
+                Rope::Leaf(leaf) => {
+                    let count = self
+                        .bufs
+                        .entry(Arc::as_ptr(&leaf.buf) as usize)
+                        .or_insert(0);
+                    *count += 1;
+                    if *count == 1 {
+                        self.total += leaf.buf.len();
+                    }
+                }
+                Rope::Node(node) => {
+                    for child in node.left.iter().chain(&node.right) {
+                        let count = self.nodes.entry(Arc::as_ptr(child) as usize).or_insert(0);
+                        *count += 1;
+                        if *count == 1 {
+                            self.total += mem::size_of::<Rope>();
+                            stack.push(child);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, rope: &Rope) {
+        self.total -= mem::size_of::<Rope>();
+        let mut stack = vec![rope];
+        while let Some(rope) = stack.pop() {
+            match rope {
+                Rope::Leaf(leaf) => {
+                    if release(&mut self.bufs, Arc::as_ptr(&leaf.buf) as usize) {
+                        self.total -= leaf.buf.len();
+                    }
+                }
+                Rope::Node(node) => {
+                    for child in node.left.iter().chain(&node.right) {
+                        if release(&mut self.nodes, Arc::as_ptr(child) as usize) {
+                            self.total -= mem::size_of::<Rope>();
+                            stack.push(child);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// drops one reference to `key`, returning whether it was the last one
+fn release(counts: &mut HashMap<usize, usize>, key: usize) -> bool {
+    let count = counts.get_mut(&key).expect("counted when added");
+    *count -= 1;
+    if *count > 0 {
+        return false;
+    }
+    counts.remove(&key);
+    true
+}
+
+#[test]
+fn test_history_undo_redo() {
+    let mut history = History::new(Rope::new("Hello"));
+    history.edit(|rope| rope.push_str(", World"));
+    history.edit(|rope| rope.push_str("!"));
+    let text = |h: &History| h.current().chars().collect::<String>();
+
+    assert!(history.undo());
+    assert_eq!(text(&history), "Hello, World");
+    assert!(history.undo());
+    assert!(!history.undo());
+    assert_eq!(text(&history), "Hello");
+
+    assert!(history.redo());
+    assert_eq!(text(&history), "Hello, World");
+    history.edit(|rope| rope.insert(0, ">"));
+    assert!(!history.redo());
+    assert_eq!(text(&history), ">Hello, World");
+}
+
+#[test]
+fn test_history_memory_budget() {
+    let mut history = History::new(Rope::chunked(&"x".repeat(64 * 1024)));
+    let base = history.memory_usage();
+    for i in 0..20 {
+        history.edit(|rope| rope.insert(i * 1000, "edit"));
+    }
+    // small edits share almost everything with the first version
+    assert!(history.memory_usage() < 2 * base);
+    assert_eq!(history.undo_len(), 20);
+
+    // the old versions hold the 64k buffers, the new ones next to nothing
+    history.edit(|rope| rope.clear());
+    history.edit(|rope| rope.push_str("fresh"));
+    history.set_memory_budget(4096);
+    assert!(history.memory_usage() <= 4096);
+    assert_eq!(history.undo_len(), 1);
+    assert_eq!(history.current().chars().collect::<String>(), "fresh");
+    assert!(history.undo());
+    assert!(history.current().is_empty());
+
+    history.set_memory_budget(0);
+    assert_eq!(history.undo_len(), 0);
+    assert!(!history.undo());
+}
+
+#[test]
+fn test_history_memory_usage_tracks_versions() {
+    // what a fresh walk over all the versions counts
+    fn walk(history: &History) -> usize {
+        let mut usage = Usage::default();
+        for rope in history
+            .undo
+            .iter()
+            .chain(Some(&history.current))
+            .chain(&history.redo)
+        {
+            usage.add(rope);
+        }
+        usage.total
+    }
+
+    let mut history = History::new(Rope::chunked(&"abc\n".repeat(4096)));
+    for i in 0..50 {
+        history.edit(|rope| rope.insert(i * 300, "edit"));
+        assert_eq!(history.memory_usage(), walk(&history));
+    }
+    history.undo();
+    history.undo();
+    history.edit(|rope| rope.remove(0..100));
+    assert_eq!(history.redo_len(), 0);
+    assert_eq!(history.memory_usage(), walk(&history));
+
+    history.set_memory_budget(history.memory_usage() - 1);
+    assert_eq!(history.memory_usage(), walk(&history));
+    assert!(history.undo_len() < 49);
+}
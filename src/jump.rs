@@ -0,0 +1,108 @@
+use crate::stats::EditInfo;
+
+/// Positions visited in a document, to go back and forth between them like
+/// the jump list of an editor. Positions are char offsets and follow the
+/// edits reported to `adjust`.
+#[derive(Debug, Clone, Default)]
+pub struct JumpList {
+    jumps: Vec<usize>,
+    // index of the current jump; equal to the length when past the newest
+    index: usize,
+}
+
+impl JumpList {
+    pub fn new() -> JumpList {
+        JumpList::default()
+    }
+
+    /// Records a jump away from `offset`. Jumps that were gone back over are
+    /// dropped, as in the history of a browser.
+    pub fn push(&mut self, offset: usize) {
+        self.jumps.truncate((self.index + 1).min(self.jumps.len()));
+        if self.jumps.last() != Some(&offset) {
+            self.jumps.push(offset);
+        }
+        self.index = self.jumps.len();
+    }
+
+    /// Goes back one jump from `current`, the position of the cursor, and
+    /// returns where to go. Going back from past the newest jump records
+    /// `current` first so that `forward` can return to it.
+    pub fn back(&mut self, current: usize) -> Option<usize> {
+        if self.index == 0 {
+            return None;
+        }
+        if self.index == self.jumps.len() {
+            self.push(current);
+            self.index -= 1;
+        }
+        if self.jumps[self.index] == current && self.index > 0 {
+            self.index -= 1;
+        }
+        Some(self.jumps[self.index])
+    }
+
+    /// Goes forward one jump after going back, and returns where to go.
+    pub fn forward(&mut self) -> Option<usize> {
+        if self.index + 1 >= self.jumps.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.jumps[self.index])
+    }
+
+    /// The recorded positions, oldest first.
+    pub fn jumps(&self) -> &[usize] {
+        &self.jumps
+    }
+
+    /// Moves the positions along with an edit. Positions inside the replaced
+    /// text move to its start.
+    pub fn adjust(&mut self, edit: &EditInfo) {
+        let range = &edit.char_range;
+        for jump in &mut self.jumps {
+            if *jump >= range.end {
+                *jump = *jump - edit.chars_removed + edit.chars_added;
+            } else if *jump > range.start {
+                *jump = range.start;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_jump_back_and_forth() {
+    let mut jumps = JumpList::new();
+    assert_eq!(jumps.back(0), None);
+
+    jumps.push(10);
+    jumps.push(50);
+    assert_eq!(jumps.back(90), Some(50));
+    assert_eq!(jumps.back(50), Some(10));
+    assert_eq!(jumps.back(10), None);
+    assert_eq!(jumps.forward(), Some(50));
+    assert_eq!(jumps.forward(), Some(90));
+    assert_eq!(jumps.forward(), None);
+
+    // a new jump drops the ones gone back over
+    jumps.back(90);
+    jumps.back(50);
+    jumps.push(20);
+    assert_eq!(jumps.jumps(), &[10, 20]);
+}
+
+#[test]
+fn test_jumps_follow_edits() {
+    use crate::rope::Rope;
+
+    let mut rope = Rope::new("one two three four");
+    let mut jumps = JumpList::new();
+    jumps.push(0);
+    jumps.push(5);
+    jumps.push(14);
+
+    let edit = rope.edit(4..8, "2 ");
+    jumps.adjust(&edit);
+    assert_eq!(jumps.jumps(), &[0, 4, 12]);
+    assert_eq!(rope.slice_to_string(12..), "four");
+}
@@ -0,0 +1,70 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// A length cached in the tree. Arithmetic on it never wraps around: going
+/// out of range panics in debug builds and saturates in release builds, so
+/// a bad offset shows up as a clamped position rather than a huge one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub(crate) struct Len(usize);
+
+impl Len {
+    pub(crate) const fn new(n: usize) -> Len {
+        Len(n)
+    }
+
+    pub(crate) const fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl Add for Len {
+    type Output = Len;
+
+    fn add(self, other: Len) -> Len {
+        debug_assert!(self.0.checked_add(other.0).is_some(), "length overflow");
+        Len(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Len {
+    type Output = Len;
+
+    fn sub(self, other: Len) -> Len {
+        debug_assert!(
+            self.0 >= other.0,
+            "length underflow: {} - {}",
+            self.0,
+            other.0
+        );
+        Len(self.0.saturating_sub(other.0))
+    }
+}
+
+impl AddAssign for Len {
+    fn add_assign(&mut self, other: Len) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Len {
+    fn sub_assign(&mut self, other: Len) {
+        *self = *self - other;
+    }
+}
+
+#[test]
+fn test_len_arithmetic() {
+    let a = Len::new(5);
+    assert_eq!((a + Len::new(3)).get(), 8);
+    assert_eq!((a - Len::new(5)).get(), 0);
+
+    let mut b = a;
+    b += Len::new(1);
+    b -= Len::new(2);
+    assert_eq!(b, Len::new(4));
+}
+
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "length underflow"))]
+fn test_len_underflow() {
+    assert_eq!((Len::new(1) - Len::new(2)).get(), 0);
+}
@@ -1,6 +1,94 @@
+mod adaptive;
+mod append;
+mod balance;
+mod buffer;
+mod builder;
+mod char_edit;
+mod chunk_cursor;
+#[cfg(feature = "compress")]
+mod cold;
+mod collect;
+mod compact;
+mod compare;
+pub mod compat;
+mod composite;
+mod count;
+mod cursor;
+mod debug;
+mod display;
+mod edit;
+mod error;
+mod flags;
+mod gap_buffer;
+mod hex;
+mod history;
+mod jump;
+mod len;
+mod line_index;
+mod line_offsets;
+mod lines;
+mod load;
+mod long_lines;
+#[cfg(feature = "compress")]
+mod lz;
+mod ops;
+mod overlay;
+mod piece_table;
+mod protect;
+mod retain;
 mod rope;
+mod search;
+mod select;
+#[cfg(feature = "serde")]
+mod serialize;
+mod session;
+mod set;
+mod slice;
+mod slice_cache;
+mod snapshot;
+mod stats;
+mod structure;
+mod transform;
+mod utf16;
+mod version;
+mod visit;
 
-pub use rope::Rope;
+pub use adaptive::{AdaptiveBuffer, Backend};
+pub use balance::{DepthError, DepthPolicy, MAX_DEPTH};
+pub use buffer::TextBuffer;
+pub use builder::RopeBuilder;
+pub use chunk_cursor::ChunkCursor;
+#[cfg(feature = "compress")]
+pub use cold::ColdRope;
+pub use compact::MIN_VIEWED;
+pub use compare::{ChangedRange, Run, ZipCompare};
+pub use composite::CompositeRope;
+pub use cursor::{Cursor, DEFAULT_TAB_WIDTH};
+pub use display::Ellipsis;
+pub use error::RopeError;
+pub use flags::Flags;
+pub use gap_buffer::{GapBuffer, PROMOTE_LEN};
+pub use hex::HexRows;
+pub use history::History;
+pub use jump::JumpList;
+pub use line_index::LineIndex;
+pub use line_offsets::LineOffsets;
+pub use overlay::{Overlay, OverlayChars, OverlayLines};
+pub use piece_table::PieceTable;
+pub use protect::ProtectedRope;
+pub use rope::{Chars, Chunks, ChunksRev, Drain, Rope};
+pub use search::{CharIndices, MatchIndices, MatchRange, RopePattern, SplitOn};
+pub use select::Unit;
+pub use session::Session;
+pub use set::RopeSet;
+pub use slice::{RopeSlice, SliceChunks};
+pub use slice_cache::SliceCache;
+pub use snapshot::{Chunk, SnapshotChars, SnapshotChunks};
+pub use stats::{EditInfo, Stats};
+pub use transform::{ExpandTabs, NormalizeLineEndings, StripBom, Transform};
+pub use utf16::Utf16Units;
+pub use version::{Version, VersionMismatch};
+pub use visit::Visitor;
 
 #[cfg(test)]
 mod tests {
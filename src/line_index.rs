@@ -0,0 +1,331 @@
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::flags::Flags;
+use crate::len::Len;
+use crate::long_lines::LineWidths;
+use crate::rope::{Leaf, Rope};
+
+const MAGIC: &[u8; 4] = b"RPL3";
+
+// per-leaf metrics, everything a leaf would otherwise scan its text for
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LeafMetrics {
+    len: usize,
+    chars: usize,
+    newlines: usize,
+    utf16: usize,
+    flags: Flags,
+    widths: LineWidths,
+}
+
+/// The leaf layout and cached line metrics of a rope, saved next to its
+/// content so a large unchanged file can be reopened without rescanning it.
+///
+/// The index records a checksum of the content it was computed for and of
+/// its own metrics, and is only applied to identical text with metrics that
+/// were not changed since.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    checksum: u64,
+    leaves: Vec<LeafMetrics>,
+}
+
+impl LineIndex {
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.checksum.to_le_bytes())?;
+        write_usize(&mut writer, self.leaves.len())?;
+        for leaf in &self.leaves {
+            write_usize(&mut writer, leaf.len)?;
+            write_usize(&mut writer, leaf.chars)?;
+            write_usize(&mut writer, leaf.newlines)?;
+            write_usize(&mut writer, leaf.utf16)?;
+            writer.write_all(&[leaf.flags.bits()])?;
+            write_usize(&mut writer, leaf.widths.first)?;
+            write_usize(&mut writer, leaf.widths.last)?;
+            write_usize(&mut writer, leaf.widths.longest)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<LineIndex> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a line index",
+            ));
+        }
+        let mut checksum = [0; 8];
+        reader.read_exact(&mut checksum)?;
+
+        let mut leaves = Vec::new();
+        for _ in 0..read_usize(&mut reader)? {
+            let len = read_usize(&mut reader)?;
+            let chars = read_usize(&mut reader)?;
+            let newlines = read_usize(&mut reader)?;
+            let utf16 = read_usize(&mut reader)?;
+            let mut flags = [0];
+            reader.read_exact(&mut flags)?;
+            let widths = LineWidths {
+                first: read_usize(&mut reader)?,
+                last: read_usize(&mut reader)?,
+                longest: read_usize(&mut reader)?,
+                breaks: newlines > 0,
+            };
+            leaves.push(LeafMetrics {
+                len,
+                chars,
+                newlines,
+                utf16,
+                flags: Flags::from_bits(flags[0]),
+                widths,
+            });
+        }
+        Ok(LineIndex {
+            checksum: u64::from_le_bytes(checksum),
+            leaves,
+        })
+    }
+}
+
+impl Rope {
+    /// Exports the leaf layout and line metrics of the rope.
+    pub fn line_index(&self) -> LineIndex {
+        let mut checksum = Digest::default();
+        let mut leaves = Vec::new();
+        let mut stack = vec![self];
+        while let Some(rope) = stack.pop() {
+            match rope {
+                Rope::Leaf(leaf) if leaf.start < leaf.end => {
+                    checksum.update(leaf.as_str().as_bytes());
+                    leaves.push(LeafMetrics {
+                        len: leaf.end - leaf.start,
+                        chars: leaf.chars.get(),
+                        newlines: leaf.newlines,
+                        utf16: leaf.utf16,
+                        flags: leaf.flags,
+                        widths: leaf.widths,
+                    });
+                }
+                Rope::Leaf(_) => {}
+                Rope::Node(node) => {
+                    stack.extend(node.right.as_deref());
+                    stack.extend(node.left.as_deref());
+                }
+            }
+        }
+        LineIndex {
+            checksum: checksum.finish(&leaves),
+            leaves,
+        }
+    }
+
+    /// Builds a rope over `text` using the metrics saved in `index` instead of
+    /// scanning the text for them. The leaves are views into `text`, which is
+    /// not copied.
+    ///
+    /// Returns `None` if the index does not belong to `text` or was damaged,
+    /// in which case the rope has to be built the usual way.
+    pub fn from_line_index(text: String, index: &LineIndex) -> Option<Rope> {
+        let mut checksum = Digest::default();
+        checksum.update(text.as_bytes());
+        let total = index
+            .leaves
+            .iter()
+            .try_fold(0usize, |sum, leaf| sum.checked_add(leaf.len));
+        if checksum.finish(&index.leaves) != index.checksum || total != Some(text.len()) {
+            return None;
+        }
+
+        let buf = Arc::new(text);
+        let mut start = 0;
+        let mut leaves = Vec::with_capacity(index.leaves.len());
+        for metrics in &index.leaves {
+            let end = start + metrics.len;
+            if !buf.is_char_boundary(end) {
+                return None;
+            }
+            leaves.push(Rope::Leaf(Leaf {
+                buf: Arc::clone(&buf),
+                start,
+                end,
+                chars: Len::new(metrics.chars),
+                flags: metrics.flags,
+                newlines: metrics.newlines,
+                utf16: metrics.utf16,
+                widths: metrics.widths,
+            }));
+            start = end;
+        }
+        Some(Rope::from_leaves(leaves))
+    }
+}
+
+// checksum of the text an index is for, then of the metrics themselves. It
+// goes a word at a time, so checking it costs less than the scan it saves,
+// and is stable across platforms and releases unlike the std hasher.
+#[derive(Default)]
+struct Digest {
+    hash: u64,
+    len: u64,
+    // bytes of an unfinished word
+    tail: [u8; 8],
+    filled: usize,
+}
+
+impl Digest {
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.len += bytes.len() as u64;
+        if self.filled > 0 {
+            let n = bytes.len().min(8 - self.filled);
+            self.tail[self.filled..self.filled + n].copy_from_slice(&bytes[..n]);
+            self.filled += n;
+            bytes = &bytes[n..];
+            if self.filled < 8 {
+                return;
+            }
+            self.mix(u64::from_le_bytes(self.tail));
+            self.filled = 0;
+        }
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.mix(u64::from_le_bytes(
+                <[u8; 8]>::try_from(word).expect("8 bytes"),
+            ));
+        }
+        let rest = words.remainder();
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    fn mix(&mut self, word: u64) {
+        self.hash = (self.hash ^ word)
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            .rotate_left(29);
+    }
+
+    fn finish(mut self, leaves: &[LeafMetrics]) -> u64 {
+        let mut tail = [0; 8];
+        tail[..self.filled].copy_from_slice(&self.tail[..self.filled]);
+        self.mix(u64::from_le_bytes(tail));
+        self.mix(self.len);
+        for leaf in leaves {
+            let widths = &leaf.widths;
+            for n in [leaf.len, leaf.chars, leaf.newlines, leaf.utf16] {
+                self.mix(n as u64);
+            }
+            for n in [widths.first, widths.last, widths.longest] {
+                self.mix(n as u64);
+            }
+            self.mix(u64::from(leaf.flags.bits()));
+        }
+        // spread the last words over all the bits
+        let mut hash = self.hash;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^ (hash >> 33)
+    }
+}
+
+// 64-bit FNV-1a, stable across platforms and releases unlike the std hasher
+pub(crate) struct Checksum(u64);
+
+impl Checksum {
+    pub(crate) fn new() -> Checksum {
+        Checksum(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn write_usize<W: Write>(writer: &mut W, n: usize) -> io::Result<()> {
+    writer.write_all(&(n as u64).to_le_bytes())
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes))
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "length out of range"))
+}
+
+#[test]
+fn test_line_index_roundtrip() {
+    let text = "fn main() {\n\tprintln!(\"é\");\n}\n".repeat(100);
+    let rope = Rope::chunked(&text);
+
+    let mut saved = Vec::new();
+    rope.line_index().write_to(&mut saved).unwrap();
+    let index = LineIndex::read_from(saved.as_slice()).unwrap();
+    assert_eq!(index, rope.line_index());
+
+    let reopened = Rope::from_line_index(text.clone(), &index).unwrap();
+    assert_eq!(reopened.chars().collect::<String>(), text);
+    assert_eq!(reopened.len_chars(), rope.len_chars());
+    assert_eq!(reopened.len_lines(), rope.len_lines());
+    assert_eq!(reopened.len_utf16(), rope.len_utf16());
+    assert_eq!(reopened.flags(), rope.flags());
+    assert_eq!(reopened.line_to_char(57), rope.line_to_char(57));
+    assert_eq!(
+        reopened.chunks().collect::<Vec<_>>(),
+        rope.chunks().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_line_index_rejects_changed_text() {
+    let rope = Rope::new("one\ntwo\n");
+    let index = rope.line_index();
+
+    assert!(Rope::from_line_index("one\ntwo!".to_string(), &index).is_none());
+    assert!(Rope::from_line_index("one\n".to_string(), &index).is_none());
+    assert!(Rope::from_line_index("one\ntwo\n".to_string(), &index).is_some());
+    assert!(LineIndex::read_from(&b"RPS1"[..]).is_err());
+}
+
+#[test]
+fn test_line_index_rejects_changed_metrics() {
+    let text = "one\ntwo\n".repeat(300);
+    let rope = Rope::chunked(&text);
+    let mut saved = Vec::new();
+    rope.line_index().write_to(&mut saved).unwrap();
+
+    // the char count of the first leaf, right after the magic, the checksum,
+    // the leaf count and the leaf length
+    let at = 4 + 8 + 8 + 8;
+    saved[at] += 1;
+    let index = LineIndex::read_from(saved.as_slice()).unwrap();
+    assert_eq!(index.checksum, rope.line_index().checksum);
+    assert!(Rope::from_line_index(text, &index).is_none());
+}
+
+#[test]
+fn test_digest_ignores_pieces() {
+    let text = b"the quick brown fox jumps over the lazy dog";
+    let mut whole = Digest::default();
+    whole.update(text);
+    let mut pieces = Digest::default();
+    for piece in text.chunks(3) {
+        pieces.update(piece);
+    }
+    assert_eq!(whole.finish(&[]), pieces.finish(&[]));
+
+    let mut shorter = Digest::default();
+    shorter.update(&text[..42]);
+    let mut padded = Digest::default();
+    padded.update(&text[..42]);
+    padded.update(&[0]);
+    assert_ne!(shorter.finish(&[]), padded.finish(&[]));
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::rope::Rope;
+use crate::stats::EditInfo;
+
+/// Line numbers to show for a document whose parts come from elsewhere, as
+/// set by `#line` directives or when files are concatenated. Ranges of chars
+/// carry an offset added to the number of the lines starting in them, and
+/// follow the edits reported to `adjust`.
+#[derive(Debug, Clone, Default)]
+pub struct LineOffsets {
+    // disjoint ranges by start, mapped to their end and offset
+    ranges: BTreeMap<usize, (usize, isize)>,
+}
+
+impl LineOffsets {
+    pub fn new() -> LineOffsets {
+        LineOffsets::default()
+    }
+
+    /// Shifts the number of the lines starting in the char range `range` by
+    /// `offset`, taking over from earlier offsets set for the same lines.
+    pub fn set(&mut self, range: Range<usize>, offset: isize) {
+        if range.is_empty() {
+            return;
+        }
+        // a range reaching in from before keeps its head, and its tail if
+        // it reaches past the end
+        if let Some((&start, &(end, old))) = self.ranges.range(..range.start).next_back() {
+            if end > range.start {
+                self.ranges.insert(start, (range.start, old));
+                if end > range.end {
+                    self.ranges.insert(range.end, (end, old));
+                }
+            }
+        }
+        // ranges starting inside are replaced, but for a tail past the end
+        let inside: Vec<usize> = self
+            .ranges
+            .range(range.clone())
+            .map(|(&start, _)| start)
+            .collect();
+        for start in inside {
+            let (end, old) = self.ranges.remove(&start).expect("range was just found");
+            if end > range.end {
+                self.ranges.insert(range.end, (end, old));
+            }
+        }
+        self.ranges.insert(range.start, (range.end, offset));
+    }
+
+    /// Drops all offsets.
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// The ranges and their offsets, in order. Where offsets were set over
+    /// each other only the parts of the earlier ranges left uncovered remain.
+    pub fn ranges(&self) -> impl Iterator<Item = (Range<usize>, isize)> + '_ {
+        self.ranges
+            .iter()
+            .map(|(&start, &(end, offset))| (start..end, offset))
+    }
+
+    /// The number to show for line `line` of `rope`. Lines outside all the
+    /// ranges keep their own number; none goes below zero.
+    pub fn display_line(&self, rope: &Rope, line: usize) -> usize {
+        let start = rope.line_to_char(line);
+        let offset = match self.ranges.range(..=start).next_back() {
+            Some((_, &(end, offset))) if start < end => offset,
+            _ => 0,
+        };
+        (line as isize).saturating_add(offset).max(0) as usize
+    }
+
+    /// Moves the ranges along with an edit. Text inserted at the end of a
+    /// range joins it; ranges that were entirely replaced are dropped.
+    pub fn adjust(&mut self, edit: &EditInfo) {
+        let replaced = &edit.char_range;
+        let moved = |pos: usize, inside: usize| match pos {
+            p if p >= replaced.end => p - edit.chars_removed + edit.chars_added,
+            p if p > replaced.start => inside,
+            p => p,
+        };
+        // moving keeps the ranges in order and apart
+        self.ranges = self
+            .ranges
+            .iter()
+            .map(|(&start, &(end, offset))| {
+                let end = moved(end, replaced.start + edit.chars_added);
+                (moved(start, replaced.start), (end, offset))
+            })
+            .filter(|&(start, (end, _))| start < end)
+            .collect();
+    }
+}
+
+#[test]
+fn test_display_line() {
+    let rope = Rope::new("a.c:1\na.c:2\n#line 40\nb.c:40\nb.c:41\n");
+    let mut offsets = LineOffsets::new();
+    assert_eq!(offsets.display_line(&rope, 1), 1);
+
+    // lines past the directive count from 40
+    offsets.set(rope.line_to_char(3)..rope.len_chars(), 37);
+    assert_eq!(offsets.display_line(&rope, 1), 1);
+    assert_eq!(offsets.display_line(&rope, 3), 40);
+    assert_eq!(offsets.display_line(&rope, 4), 41);
+
+    // a later offset wins, and numbers stop at zero
+    offsets.set(rope.line_to_char(4)..rope.line_to_char(5), -10);
+    assert_eq!(offsets.display_line(&rope, 4), 0);
+    assert_eq!(offsets.display_line(&rope, 3), 40);
+}
+
+#[test]
+fn test_line_offsets_follow_edits() {
+    let mut rope = Rope::new("one\ntwo\nthree\nfour\n");
+    let mut offsets = LineOffsets::new();
+    offsets.set(rope.line_to_char(2)..rope.line_to_char(4), 100);
+
+    // a new line above moves the range down
+    let edit = rope.edit(0..0, "zero\n");
+    offsets.adjust(&edit);
+    assert_eq!(offsets.display_line(&rope, 3), 103);
+    assert_eq!(offsets.display_line(&rope, 2), 2);
+
+    // and one appended at its end joins it
+    let at = rope.line_to_char(5);
+    let edit = rope.edit(at..at, "five\n");
+    offsets.adjust(&edit);
+    assert_eq!(offsets.display_line(&rope, 5), 105);
+    assert_eq!(offsets.display_line(&rope, 6), 6);
+
+    let edit = rope.edit(rope.line_to_char(3)..rope.len(), "");
+    offsets.adjust(&edit);
+    assert_eq!(offsets.ranges().count(), 0);
+}
+
+#[test]
+fn test_line_offsets_replace_overlaps() {
+    let mut offsets = LineOffsets::new();
+    offsets.set(0..10, 5);
+    offsets.set(3..6, 1);
+    offsets.set(8..12, 2);
+    let ranges: Vec<_> = offsets.ranges().collect();
+    assert_eq!(ranges, vec![(0..3, 5), (3..6, 1), (6..8, 5), (8..12, 2)]);
+
+    offsets.set(2..9, 7);
+    let ranges: Vec<_> = offsets.ranges().collect();
+    assert_eq!(ranges, vec![(0..2, 5), (2..9, 7), (9..12, 2)]);
+
+    offsets.set(0..20, 0);
+    assert_eq!(offsets.ranges().collect::<Vec<_>>(), vec![(0..20, 0)]);
+}
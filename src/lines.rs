@@ -0,0 +1,324 @@
+use std::ops::Range;
+
+use crate::line_index::Checksum;
+use crate::rope::Rope;
+
+impl Rope {
+    /// Number of lines, which is one more than the number of line breaks.
+    pub fn len_lines(&self) -> usize {
+        self.newlines() + 1
+    }
+
+    /// Char offset at which `line` starts. Lines past the end map to the
+    /// length of the rope.
+    pub fn line_to_char(&self, line: usize) -> usize {
+        if line == 0 {
+            return 0;
+        }
+        match self {
+            Rope::Leaf(_) => self
+                .chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '\n')
+                .nth(line - 1)
+                .map_or(self.len_chars(), |(i, _)| i + 1),
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let left_lines = left.newlines();
+                if line <= left_lines {
+                    return left.line_to_char(line);
+                }
+                left.len_chars()
+                    + node
+                        .right
+                        .as_ref()
+                        .map_or(0, |r| r.line_to_char(line - left_lines))
+            }
+        }
+    }
+
+    /// Line containing the char at `char_idx`.
+    pub fn char_to_line(&self, char_idx: usize) -> usize {
+        match self {
+            Rope::Leaf(_) => self.chars().take(char_idx).filter(|c| *c == '\n').count(),
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let left_chars = left.len_chars();
+                if char_idx < left_chars {
+                    return left.char_to_line(char_idx);
+                }
+                left.newlines()
+                    + node
+                        .right
+                        .as_ref()
+                        .map_or(0, |r| r.char_to_line(char_idx - left_chars))
+            }
+        }
+    }
+
+    /// Length of `line` in chars, without its line break. The tree's line
+    /// counts lead to the leaves at the line's start and end, and only those
+    /// two leaves are scanned; the line is not copied.
+    pub fn line_len_chars(&self, line: usize) -> usize {
+        let (start, end) = self.line_bounds(line);
+        end - start
+    }
+
+    /// Length of `line` in bytes, without its line break.
+    pub fn line_len_bytes(&self, line: usize) -> usize {
+        let (start, end) = self.line_bounds(line);
+        self.char_to_byte(end) - self.char_to_byte(start)
+    }
+
+    // char offsets of the start and end of `line`, line break excluded
+    fn line_bounds(&self, line: usize) -> (usize, usize) {
+        let start = self.line_to_char(line);
+        let mut end = self.line_to_char(line + 1);
+        if line + 1 < self.len_lines() {
+            end -= 1;
+        }
+        (start, end.max(start))
+    }
+
+    /// Lines within `lines` ending in a space or tab. Subtrees whose cached
+    /// flags rule out trailing whitespace are skipped without scanning.
+    pub fn lines_with_trailing_whitespace(
+        &self,
+        lines: Range<usize>,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let range = self.line_to_char(lines.start)..self.line_to_char(lines.end);
+        let mut found = Vec::new();
+        self.trailing_ws_in(self, 0, &range, &mut found);
+        found.into_iter().map(move |at| self.char_to_line(at))
+    }
+
+    // collects char offsets of whitespace ending a line, `base` being the
+    // char offset of this subtree in the root
+    fn trailing_ws_in(
+        &self,
+        root: &Rope,
+        base: usize,
+        range: &Range<usize>,
+        found: &mut Vec<usize>,
+    ) {
+        if !self.trailing_ws() || base >= range.end {
+            return;
+        }
+        match self {
+            Rope::Leaf(leaf) => {
+                let mut chars = leaf.as_str().chars().enumerate().peekable();
+                while let Some((i, c)) = chars.next() {
+                    let at = base + i;
+                    if at < range.start || at >= range.end || (c != ' ' && c != '\t') {
+                        continue;
+                    }
+                    let ends_line = match chars.peek() {
+                        Some((_, next)) => *next == '\n',
+                        // the line break may start the next leaf
+                        None => root.chars_at(at + 1).next().is_none_or(|c| c == '\n'),
+                    };
+                    if ends_line {
+                        found.push(at);
+                    }
+                }
+            }
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let left_chars = left.len_chars();
+                if base + left_chars > range.start {
+                    left.trailing_ws_in(root, base, range, found);
+                }
+                if let Some(right) = node.right.as_ref() {
+                    right.trailing_ws_in(root, base + left_chars, range, found);
+                }
+            }
+        }
+    }
+
+    /// Lines within `lines` starting with `prefix`, such as `fn ` or `#`, for
+    /// outlines and folding. The text is scanned chunk by chunk from the
+    /// first line, jumping to the next line break as soon as a line stops
+    /// matching.
+    pub fn lines_matching_prefix(
+        &self,
+        prefix: &str,
+        lines: Range<usize>,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let end = lines.end.min(self.len_lines());
+        let prefix = prefix.as_bytes();
+        let mut found = Vec::new();
+        let mut line = lines.start;
+        // bytes of the prefix matched so far on the current line, none once
+        // it is known not to match
+        let mut matched = Some(0);
+        let (chunks, skip) = self.chunks_at_byte(self.char_to_byte(self.line_to_char(line)));
+        'chunks: for (i, chunk) in chunks.enumerate() {
+            let mut bytes = &chunk.as_bytes()[if i == 0 { skip } else { 0 }..];
+            while line < end {
+                if matched == Some(prefix.len()) {
+                    found.push(line);
+                    matched = None;
+                }
+                let Some(k) = matched else {
+                    match bytes.iter().position(|&b| b == b'\n') {
+                        Some(at) => bytes = &bytes[at + 1..],
+                        None => continue 'chunks,
+                    }
+                    line += 1;
+                    matched = Some(0);
+                    continue;
+                };
+                let Some((&b, rest)) = bytes.split_first() else {
+                    continue 'chunks;
+                };
+                bytes = rest;
+                if b == b'\n' {
+                    line += 1;
+                    matched = Some(0);
+                } else if b == prefix[k] {
+                    matched = Some(k + 1);
+                } else {
+                    matched = None;
+                }
+            }
+            break;
+        }
+        // the last line may match right at the end of the text
+        if line < end && matched == Some(prefix.len()) {
+            found.push(line);
+        }
+        found.into_iter()
+    }
+
+    /// A hash of each line within `lines`, without its line break, for diff
+    /// and merge algorithms to compare lines by. The hash is 64-bit FNV-1a,
+    /// stable across platforms and releases. The text is hashed chunk by
+    /// chunk, without copying lines out.
+    pub fn line_hashes(&self, lines: Range<usize>) -> Vec<u64> {
+        let end = lines.end.min(self.len_lines());
+        if lines.start >= end {
+            return Vec::new();
+        }
+        let mut hashes = Vec::with_capacity(end - lines.start);
+        let mut hash = Checksum::new();
+        let (chunks, skip) = self.chunks_at_byte(self.char_to_byte(self.line_to_char(lines.start)));
+        for (i, chunk) in chunks.enumerate() {
+            let mut bytes = &chunk.as_bytes()[if i == 0 { skip } else { 0 }..];
+            while let Some(at) = bytes.iter().position(|&b| b == b'\n') {
+                hash.update(&bytes[..at]);
+                hashes.push(hash.finish());
+                if hashes.len() == end - lines.start {
+                    return hashes;
+                }
+                hash = Checksum::new();
+                bytes = &bytes[at + 1..];
+            }
+            hash.update(bytes);
+        }
+        // the last line has no line break
+        hashes.push(hash.finish());
+        hashes
+    }
+
+    /// Chars of `line`, without its line break.
+    pub fn line_chars(&self, line: usize) -> impl Iterator<Item = char> + '_ {
+        self.chars_at(self.line_to_char(line))
+            .take_while(|c| *c != '\n')
+    }
+}
+
+#[test]
+fn test_line_to_char() {
+    let rope = Rope::from_leaves(vec![Rope::new("ab\ncd"), Rope::new("e\n\nfg")]);
+
+    assert_eq!(rope.len_lines(), 4);
+    assert_eq!(rope.line_to_char(0), 0);
+    assert_eq!(rope.line_to_char(1), 3);
+    assert_eq!(rope.line_to_char(2), 7);
+    assert_eq!(rope.line_to_char(3), 8);
+    assert_eq!(rope.line_to_char(4), 10);
+}
+
+#[test]
+fn test_char_to_line() {
+    let rope = Rope::from_leaves(vec![Rope::new("ab\ncd"), Rope::new("e\n\nfg")]);
+
+    assert_eq!(rope.char_to_line(0), 0);
+    assert_eq!(rope.char_to_line(2), 0);
+    assert_eq!(rope.char_to_line(3), 1);
+    assert_eq!(rope.char_to_line(6), 1);
+    assert_eq!(rope.char_to_line(7), 2);
+    assert_eq!(rope.char_to_line(9), 3);
+    assert_eq!(rope.line_chars(1).collect::<String>(), "cde");
+}
+
+#[test]
+fn test_line_len() {
+    let rope = Rope::from_leaves(vec![Rope::new("ab\ncö"), Rope::new("e\n\nfg")]);
+
+    assert_eq!(rope.line_len_chars(0), 2);
+    assert_eq!(rope.line_len_chars(1), 3);
+    assert_eq!(rope.line_len_bytes(1), 4);
+    assert_eq!(rope.line_len_chars(2), 0);
+    assert_eq!(rope.line_len_chars(3), 2);
+    assert_eq!(rope.line_len_chars(4), 0);
+}
+
+#[test]
+fn test_lines_with_trailing_whitespace() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("clean\ndirty \n"),
+        Rope::new("clean\nsplit\t"),
+        Rope::new("\nclean\nlast "),
+    ]);
+
+    let lines: Vec<usize> = rope.lines_with_trailing_whitespace(0..6).collect();
+    assert_eq!(lines, vec![1, 3, 5]);
+
+    let lines: Vec<usize> = rope.lines_with_trailing_whitespace(2..4).collect();
+    assert_eq!(lines, vec![3]);
+
+    let clean = Rope::from_leaves(vec![Rope::new("a\n"), Rope::new("b c\n")]);
+    assert!(!clean.trailing_ws());
+    assert_eq!(clean.lines_with_trailing_whitespace(0..3).count(), 0);
+}
+
+#[test]
+fn test_lines_matching_prefix() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("# Title\ntext\n## Sec"),
+        Rope::new("tion\n#"),
+        Rope::new("# Other\n##"),
+    ]);
+
+    let lines: Vec<usize> = rope.lines_matching_prefix("#", 0..10).collect();
+    assert_eq!(lines, vec![0, 2, 3, 4]);
+    let lines: Vec<usize> = rope.lines_matching_prefix("## ", 0..10).collect();
+    assert_eq!(lines, vec![2, 3]);
+    let lines: Vec<usize> = rope.lines_matching_prefix("##", 3..4).collect();
+    assert_eq!(lines, vec![3]);
+    let lines: Vec<usize> = rope.lines_matching_prefix("", 1..3).collect();
+    assert_eq!(lines, vec![1, 2]);
+    assert_eq!(rope.lines_matching_prefix("fn ", 0..10).count(), 0);
+}
+
+#[test]
+fn test_line_hashes() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("one\ntw"),
+        Rope::new("o\none\n"),
+        Rope::new("two"),
+    ]);
+    let fnv = |s: &str| {
+        let mut hash = Checksum::new();
+        hash.update(s.as_bytes());
+        hash.finish()
+    };
+
+    let hashes = rope.line_hashes(0..10);
+    assert_eq!(hashes, vec![fnv("one"), fnv("two"), fnv("one"), fnv("two")]);
+    assert_eq!(rope.line_hashes(1..3), vec![fnv("two"), fnv("one")]);
+    assert_eq!(rope.line_hashes(3..4), vec![fnv("two")]);
+    assert!(rope.line_hashes(4..9).is_empty());
+    assert_eq!(Rope::new("a\n").line_hashes(0..2), vec![fnv("a"), fnv("")]);
+}
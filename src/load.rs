@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::str::{self, FromStr, Utf8Error};
+use std::sync::Arc;
+use std::thread;
+
+use crate::rope::{Leaf, Rope, MAX_LEAF_LEN};
+
+impl Rope {
+    /// Builds a rope out of UTF-8 bytes, such as the contents of a file.
+    ///
+    /// The input is validated leaf by leaf, and chunks that turn out to be
+    /// ASCII skip counting their chars and UTF-16 units.
+    pub fn from_utf8(bytes: &[u8]) -> Result<Rope, Utf8Error> {
+        Rope::from_utf8_parallel(bytes, 1)
+    }
+
+    /// Like `from_utf8`, validating the chunks on up to `threads` threads.
+    pub fn from_utf8_parallel(bytes: &[u8], threads: usize) -> Result<Rope, Utf8Error> {
+        let chunks = chunks(bytes);
+        let per_thread = chunks.len().div_ceil(threads.max(1)).max(1);
+
+        let leaves: Option<Vec<Rope>> = if chunks.len() <= per_thread {
+            chunks.into_iter().map(leaf).collect()
+        } else {
+            thread::scope(|scope| {
+                let handles: Vec<_> = chunks
+                    .chunks(per_thread)
+                    .map(|group| scope.spawn(move || group.iter().copied().map(leaf).collect()))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("validation thread panicked"))
+                    .collect::<Option<Vec<Vec<Rope>>>>()
+                    .map(|groups| groups.into_iter().flatten().collect())
+            })
+        };
+
+        match leaves {
+            Some(leaves) => Ok(Rope::from_leaves(leaves)),
+            // a chunk was rejected, so the whole input is too. validating it
+            // again gives the error its offset in the whole input
+            None => Err(str::from_utf8(bytes).expect_err("a chunk was not UTF-8")),
+        }
+    }
+}
+
+/// Takes over the string as the buffer of the rope, without copying it. The
+/// leaves are views into it.
+impl From<String> for Rope {
+    fn from(s: String) -> Rope {
+        let buf = Arc::new(s);
+        let mut start = 0;
+        let mut leaves = Vec::new();
+        for chunk in chunks(buf.as_bytes()) {
+            leaves.push(Rope::Leaf(Leaf::view(
+                Arc::clone(&buf),
+                start,
+                start + chunk.len(),
+            )));
+            start += chunk.len();
+        }
+        Rope::from_leaves(leaves)
+    }
+}
+
+impl From<Box<str>> for Rope {
+    fn from(s: Box<str>) -> Rope {
+        Rope::from(s.into_string())
+    }
+}
+
+impl From<Cow<'_, str>> for Rope {
+    fn from(s: Cow<'_, str>) -> Rope {
+        match s {
+            Cow::Owned(s) => Rope::from(s),
+            Cow::Borrowed(s) => Rope::chunked(s),
+        }
+    }
+}
+
+impl Rope {
+    /// Turns the rope into a string. A rope made of a single leaf that no
+    /// other rope shares hands over its buffer without copying it; any other
+    /// rope is copied out chunk by chunk.
+    pub fn into_string(self) -> String {
+        let leaf = match self {
+            Rope::Leaf(leaf) => leaf,
+            rope => return rope.to_string(),
+        };
+        match Arc::try_unwrap(leaf.buf) {
+            Ok(mut buf) => {
+                buf.truncate(leaf.end);
+                buf.drain(..leaf.start);
+                buf
+            }
+            Err(buf) => buf[leaf.start..leaf.end].to_string(),
+        }
+    }
+}
+
+impl Rope {
+    /// The text of the rope as one `&str`, if it lives in a single leaf as
+    /// small documents that were never split up do.
+    pub fn as_contiguous(&self) -> Option<&str> {
+        match self {
+            Rope::Leaf(leaf) => Some(leaf.as_str()),
+            Rope::Node(_) => None,
+        }
+    }
+
+    /// The text of the rope, borrowed when it is contiguous and copied out
+    /// otherwise.
+    pub fn to_cow(&self) -> Cow<'_, str> {
+        match self.as_contiguous() {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(self.to_string()),
+        }
+    }
+}
+
+impl From<Rope> for String {
+    fn from(rope: Rope) -> String {
+        rope.into_string()
+    }
+}
+
+impl FromStr for Rope {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Rope, Infallible> {
+        Ok(Rope::chunked(s))
+    }
+}
+
+// cuts `bytes` into pieces of at most MAX_LEAF_LEN bytes, never in the middle
+// of a char when the input is valid UTF-8
+fn chunks(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::with_capacity(bytes.len() / MAX_LEAF_LEN + 1);
+    let mut rest = bytes;
+    while rest.len() > MAX_LEAF_LEN {
+        let mut at = MAX_LEAF_LEN;
+        // back off continuation bytes, at most three for a valid char
+        while at > MAX_LEAF_LEN - 3 && rest[at] & 0xc0 == 0x80 {
+            at -= 1;
+        }
+        let (head, tail) = rest.split_at(at);
+        chunks.push(head);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+fn leaf(chunk: &[u8]) -> Option<Rope> {
+    if chunk.is_ascii() {
+        let s = str::from_utf8(chunk).expect("ASCII is UTF-8");
+        return Some(Rope::Leaf(Leaf::ascii(s)));
+    }
+    str::from_utf8(chunk).ok().map(Rope::new)
+}
+
+#[test]
+fn test_from_utf8() {
+    let text = "Grüße, World!\n".repeat(MAX_LEAF_LEN / 4);
+    let rope = Rope::from_utf8(text.as_bytes()).unwrap();
+
+    assert_eq!(rope.chars().collect::<String>(), text);
+    assert_eq!(rope.len_chars(), text.chars().count());
+    assert_eq!(rope.len_utf16(), text.encode_utf16().count());
+    assert!(rope.chunks().all(|c| c.len() <= MAX_LEAF_LEN));
+    assert!(!rope.is_ascii());
+
+    let rope = Rope::from_utf8(b"plain\tascii\n").unwrap();
+    assert!(rope.is_ascii());
+    assert!(rope.has_tabs());
+    assert_eq!(rope.len_chars(), 12);
+    assert!(Rope::from_utf8(b"").unwrap().is_empty());
+}
+
+#[test]
+fn test_from_utf8_parallel() {
+    let text = "日本語 and ascii\n".repeat(MAX_LEAF_LEN);
+    let rope = Rope::from_utf8_parallel(text.as_bytes(), 4).unwrap();
+
+    assert_eq!(rope.chars().collect::<String>(), text);
+    assert_eq!(rope.len_lines(), MAX_LEAF_LEN + 1);
+}
+
+#[test]
+fn test_from_utf8_invalid() {
+    let mut bytes = "a".repeat(3 * MAX_LEAF_LEN).into_bytes();
+    bytes[2 * MAX_LEAF_LEN + 5] = 0xff;
+
+    let err = Rope::from_utf8_parallel(&bytes, 3).unwrap_err();
+    assert_eq!(err.valid_up_to(), 2 * MAX_LEAF_LEN + 5);
+    assert!(Rope::from_utf8(b"gr\xc3").is_err());
+}
+
+#[test]
+fn test_from_string() {
+    let text = "Grüße, World!\n".repeat(MAX_LEAF_LEN / 4);
+    let ptr = text.as_ptr();
+    let rope = Rope::from(text.clone());
+
+    assert_eq!(rope.to_string(), text);
+    assert!(rope.chunks().all(|c| c.len() <= MAX_LEAF_LEN));
+
+    // the leaves view the string that was handed over
+    let rope = Rope::from(text);
+    assert_eq!(rope.chunks().next().unwrap().as_ptr(), ptr);
+
+    let boxed: Box<str> = "boxed".into();
+    let ptr = boxed.as_ptr();
+    assert_eq!(Rope::from(boxed).chunks().next().unwrap().as_ptr(), ptr);
+    assert_eq!(Rope::from(Cow::Borrowed("cow")).to_string(), "cow");
+    assert!(Rope::from(String::new()).is_empty());
+}
+
+#[test]
+fn test_into_string() {
+    let text = String::from("Grüße, World!");
+    let ptr = text.as_ptr();
+    let rope = Rope::from(text);
+    let unwrapped = rope.into_string();
+    assert_eq!(unwrapped, "Grüße, World!");
+    assert_eq!(unwrapped.as_ptr(), ptr);
+
+    // a shared leaf is copied, leaving the other rope alone
+    let rope = Rope::new("shared");
+    let other = rope.clone();
+    assert_eq!(rope.into_string(), "shared");
+    assert_eq!(other.to_string(), "shared");
+
+    let mut rope = Rope::new("Hello, World!");
+    rope.remove(0..7);
+    assert_eq!(String::from(rope), "World!");
+
+    let rope = Rope::from_leaves(vec![Rope::new("Grü"), Rope::new("ße")]);
+    assert_eq!(rope.into_string(), "Grüße");
+}
+
+#[test]
+fn test_as_contiguous() {
+    let rope = Rope::new("Grüße");
+    assert_eq!(rope.as_contiguous(), Some("Grüße"));
+    assert!(matches!(rope.to_cow(), Cow::Borrowed("Grüße")));
+
+    let rope = Rope::from_leaves(vec![Rope::new("Grü"), Rope::new("ße")]);
+    assert_eq!(rope.as_contiguous(), None);
+    assert!(matches!(rope.to_cow(), Cow::Owned(s) if s == "Grüße"));
+}
+
+#[test]
+fn test_parse() {
+    let rope: Rope = "hello".parse().unwrap();
+    assert_eq!(rope.to_string(), "hello");
+
+    let text = "x".repeat(3 * MAX_LEAF_LEN);
+    assert_eq!(text.parse::<Rope>().unwrap().chunks().count(), 3);
+}
@@ -0,0 +1,149 @@
+use crate::rope::Rope;
+
+// line lengths in chars, line feeds excluded, cached for every subtree so
+// that the longest line is known without scanning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct LineWidths {
+    // length of the text before the first line feed, or of all of it
+    pub(crate) first: usize,
+    // length of the text after the last line feed, or of all of it
+    pub(crate) last: usize,
+    pub(crate) longest: usize,
+    pub(crate) breaks: bool,
+}
+
+impl LineWidths {
+    pub(crate) fn of(s: &str) -> LineWidths {
+        let mut widths = LineWidths::default();
+        let mut run = 0;
+        for c in s.chars() {
+            if c != '\n' {
+                run += 1;
+                continue;
+            }
+            if !widths.breaks {
+                widths.first = run;
+                widths.breaks = true;
+            }
+            widths.longest = widths.longest.max(run);
+            run = 0;
+        }
+        if !widths.breaks {
+            widths.first = run;
+        }
+        widths.last = run;
+        widths.longest = widths.longest.max(run);
+        widths
+    }
+
+    // widths of the concatenation of two texts
+    pub(crate) fn join(left: LineWidths, right: LineWidths) -> LineWidths {
+        let middle = left.last + right.first;
+        LineWidths {
+            first: if left.breaks { left.first } else { middle },
+            last: if right.breaks { right.last } else { middle },
+            longest: left.longest.max(right.longest).max(middle),
+            breaks: left.breaks || right.breaks,
+        }
+    }
+}
+
+impl Rope {
+    /// Length in chars of the longest line, line break excluded. Cached, so
+    /// this is O(1).
+    pub fn longest_line(&self) -> usize {
+        self.widths().longest
+    }
+
+    /// Whether any line is longer than `threshold` chars, for instance to
+    /// switch to a cheaper rendering for minified files.
+    pub fn has_long_lines(&self, threshold: usize) -> bool {
+        self.longest_line() > threshold
+    }
+
+    /// Lines longer than `threshold` chars. Subtrees holding only shorter
+    /// lines are skipped without scanning them.
+    pub fn long_lines(&self, threshold: usize) -> impl Iterator<Item = usize> {
+        let mut scan = LongLines {
+            threshold,
+            line: 0,
+            run: 0,
+            found: Vec::new(),
+        };
+        scan.visit(self);
+        if scan.run > threshold {
+            scan.found.push(scan.line);
+        }
+        scan.found.into_iter()
+    }
+}
+
+struct LongLines {
+    threshold: usize,
+    // line being scanned and its length so far
+    line: usize,
+    run: usize,
+    found: Vec<usize>,
+}
+
+impl LongLines {
+    fn visit(&mut self, rope: &Rope) {
+        let widths = rope.widths();
+        if widths.longest <= self.threshold && self.run + widths.first <= self.threshold {
+            if widths.breaks {
+                self.line += rope.newlines();
+                self.run = widths.last;
+            } else {
+                self.run += widths.first;
+            }
+            return;
+        }
+        match rope {
+            Rope::Leaf(leaf) => {
+                for c in leaf.as_str().chars() {
+                    if c != '\n' {
+                        self.run += 1;
+                        continue;
+                    }
+                    if self.run > self.threshold {
+                        self.found.push(self.line);
+                    }
+                    self.line += 1;
+                    self.run = 0;
+                }
+            }
+            Rope::Node(node) => {
+                for child in node.left.iter().chain(&node.right) {
+                    self.visit(child);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_line_widths() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("short\nmuch long"),
+        Rope::new("er line\nx"),
+        Rope::new("y\n\nlast"),
+    ]);
+
+    assert_eq!(rope.longest_line(), 16);
+    assert_eq!(
+        rope.widths(),
+        LineWidths::of("short\nmuch longer line\nxy\n\nlast")
+    );
+    assert!(rope.has_long_lines(15));
+    assert!(!rope.has_long_lines(16));
+    assert_eq!(rope.long_lines(4).collect::<Vec<_>>(), vec![0, 1]);
+    assert_eq!(rope.long_lines(5).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(rope.long_lines(1).collect::<Vec<_>>(), vec![0, 1, 2, 4]);
+    assert_eq!(rope.long_lines(16).count(), 0);
+
+    let mut rope = rope;
+    rope.remove(..6);
+    assert_eq!(rope.longest_line(), 16);
+    rope.remove(..17);
+    assert_eq!(rope.longest_line(), 4);
+}
@@ -0,0 +1,129 @@
+// a small LZ77 codec in the spirit of LZ4, for text at rest: fast to decode,
+// no dependency. A block is a series of sequences, each a token byte holding
+// the literal and match lengths, the literals, then the match as a 16-bit
+// offset back into the output. The last sequence only has literals.
+
+const MIN_MATCH: usize = 4;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+
+pub(crate) fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2);
+    // last position + 1 each 4-byte prefix was seen at, 0 for none
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let (mut anchor, mut i) = (0, 0);
+    while i + MIN_MATCH <= input.len() {
+        let prefix = &input[i..i + MIN_MATCH];
+        let word = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]);
+        let slot = (word.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[slot], i + 1);
+        if candidate > 0 {
+            let at = candidate - 1;
+            if i - at <= MAX_OFFSET && &input[at..at + MIN_MATCH] == prefix {
+                let mut len = MIN_MATCH;
+                while i + len < input.len() && input[at + len] == input[i + len] {
+                    len += 1;
+                }
+                push_sequence(&mut out, &input[anchor..i], Some((i - at, len)));
+                i += len;
+                anchor = i;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    push_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+// the bytes `compress` was given, or none if `packed` is not its output
+pub(crate) fn decompress(packed: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(packed.len() * 2);
+    let mut pos = 0;
+    loop {
+        let token = *packed.get(pos)?;
+        pos += 1;
+
+        let literals = read_len(packed, &mut pos, usize::from(token >> 4))?;
+        let end = pos.checked_add(literals)?;
+        out.extend_from_slice(packed.get(pos..end)?);
+        pos = end;
+        if pos == packed.len() {
+            return Some(out);
+        }
+
+        let offset = packed.get(pos..pos + 2)?;
+        let offset = usize::from(u16::from_le_bytes([offset[0], offset[1]]));
+        pos += 2;
+        let len = read_len(packed, &mut pos, usize::from(token & 15))? + MIN_MATCH;
+        if offset == 0 || offset > out.len() {
+            return None;
+        }
+        // byte by byte, as the match may overlap the bytes it produces
+        let start = out.len() - offset;
+        for k in 0..len {
+            out.push(out[start + k]);
+        }
+    }
+}
+
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_len = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push((literals.len().min(15) as u8) << 4 | match_len.min(15) as u8);
+    push_len(out, literals.len());
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        push_len(out, match_len);
+    }
+}
+
+// lengths of 15 and up spill into extra bytes after the token
+fn push_len(out: &mut Vec<u8>, len: usize) {
+    if len < 15 {
+        return;
+    }
+    let mut rest = len - 15;
+    while rest >= 255 {
+        out.push(255);
+        rest -= 255;
+    }
+    out.push(rest as u8);
+}
+
+fn read_len(packed: &[u8], pos: &mut usize, nibble: usize) -> Option<usize> {
+    let mut len = nibble;
+    if nibble == 15 {
+        loop {
+            let byte = *packed.get(*pos)?;
+            *pos += 1;
+            len = len.checked_add(usize::from(byte))?;
+            if byte < 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
+
+#[test]
+fn test_lz_roundtrip() {
+    let log = "2024-01-01 12:00:00 INFO request served in 3ms\n".repeat(500);
+    let mixed: Vec<u8> = (0..5000u32).map(|i| (i * 7919 % 251) as u8).collect();
+    for input in [
+        &b""[..],
+        b"a",
+        b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        b"abcabcabcabcXabcabcabc",
+        log.as_bytes(),
+        &mixed,
+    ] {
+        let packed = compress(input);
+        assert_eq!(decompress(&packed).as_deref(), Some(input));
+    }
+    assert!(compress(log.as_bytes()).len() < log.len() / 10);
+
+    assert_eq!(decompress(&[]), None);
+    assert_eq!(decompress(&[0x10]), None);
+    assert_eq!(decompress(&[0x01, 1, 0]), None);
+}
@@ -0,0 +1,44 @@
+use std::ops::{Add, AddAssign};
+
+use crate::rope::Rope;
+
+impl Add<Rope> for Rope {
+    type Output = Rope;
+
+    fn add(mut self, other: Rope) -> Rope {
+        self.append(other);
+        self
+    }
+}
+
+impl Add<&str> for Rope {
+    type Output = Rope;
+
+    fn add(mut self, s: &str) -> Rope {
+        self.push_str(s);
+        self
+    }
+}
+
+impl AddAssign<Rope> for Rope {
+    fn add_assign(&mut self, other: Rope) {
+        self.append(other);
+    }
+}
+
+impl AddAssign<&str> for Rope {
+    fn add_assign(&mut self, s: &str) {
+        self.push_str(s);
+    }
+}
+
+#[test]
+fn test_add() {
+    let rope = Rope::new("Hello") + ", " + Rope::new("World");
+    assert_eq!(rope.chars().collect::<String>(), "Hello, World");
+
+    let mut rope = rope;
+    rope += "!";
+    rope += Rope::concat(Rope::new(" Bye"), Rope::new("."));
+    assert_eq!(rope.chars().collect::<String>(), "Hello, World! Bye.");
+}
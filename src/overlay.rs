@@ -0,0 +1,204 @@
+use crate::rope::{Chars, Rope};
+
+/// A read-only view that composes virtual text (inlay hints, injected
+/// headers, ...) over a base rope without touching the base itself.
+///
+/// Offsets are char offsets. Virtual text anchored at base offset `n` is
+/// rendered right before the base char at `n`.
+#[derive(Debug)]
+pub struct Overlay<'a> {
+    base: &'a Rope,
+    // sorted by anchor, insertion order kept for equal anchors
+    inserts: Vec<(usize, String)>,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(base: &'a Rope) -> Overlay<'a> {
+        Overlay {
+            base,
+            inserts: Vec::new(),
+        }
+    }
+
+    pub fn base(&self) -> &'a Rope {
+        self.base
+    }
+
+    /// Adds virtual text at the given base offset.
+    pub fn insert(&mut self, offset: usize, s: &str) {
+        let i = self.inserts.partition_point(|(at, _)| *at <= offset);
+        self.inserts.insert(i, (offset, s.to_string()));
+    }
+
+    /// Removes all virtual text, leaving a plain view of the base.
+    pub fn clear(&mut self) {
+        self.inserts.clear();
+    }
+
+    /// Number of chars in the composed text.
+    pub fn len(&self) -> usize {
+        self.base.chars().count() + self.virtual_len(usize::MAX)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // number of virtual chars anchored at or before `offset`
+    fn virtual_len(&self, offset: usize) -> usize {
+        self.inserts
+            .iter()
+            .take_while(|(at, _)| *at <= offset)
+            .map(|(_, s)| s.chars().count())
+            .sum()
+    }
+
+    /// Maps a base offset to its position in the composed text.
+    pub fn to_overlay(&self, offset: usize) -> usize {
+        offset + self.virtual_len(offset)
+    }
+
+    /// Maps a composed offset back to the base. Returns None when the
+    /// offset points into virtual text.
+    pub fn to_base(&self, offset: usize) -> Option<usize> {
+        let mut shift = 0;
+        for (at, s) in self.inserts.iter() {
+            let start = at + shift;
+            if offset < start {
+                break;
+            }
+            let len = s.chars().count();
+            if offset < start + len {
+                return None;
+            }
+            shift += len;
+        }
+        Some(offset - shift)
+    }
+
+    /// Iterates over the chars of the composed text.
+    pub fn chars(&self) -> OverlayChars<'_> {
+        OverlayChars {
+            base: self.base.chars(),
+            pos: 0,
+            inserts: &self.inserts,
+            virt: "".chars(),
+        }
+    }
+
+    /// Iterates over the lines of the composed text, without line breaks.
+    pub fn lines(&self) -> OverlayLines<'_> {
+        OverlayLines {
+            chars: self.chars(),
+            done: false,
+        }
+    }
+
+    /// Width in chars of the widest composed line.
+    pub fn width(&self) -> usize {
+        self.lines().map(|l| l.chars().count()).max().unwrap_or(0)
+    }
+}
+
+pub struct OverlayChars<'a> {
+    base: Chars<'a>,
+    pos: usize,
+    inserts: &'a [(usize, String)],
+    virt: std::str::Chars<'a>,
+}
+
+impl<'a> Iterator for OverlayChars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.virt.next() {
+                return Some(c);
+            }
+            match self.inserts.first() {
+                Some((at, s)) if *at <= self.pos => {
+                    self.virt = s.chars();
+                    self.inserts = &self.inserts[1..];
+                }
+                _ => break,
+            }
+        }
+        match self.base.next() {
+            Some(c) => {
+                self.pos += 1;
+                Some(c)
+            }
+            None => {
+                // flush virtual text anchored past the end of the base
+                let (_, s) = self.inserts.first()?;
+                self.inserts = &self.inserts[1..];
+                self.virt = s.chars();
+                self.next()
+            }
+        }
+    }
+}
+
+pub struct OverlayLines<'a> {
+    chars: OverlayChars<'a>,
+    done: bool,
+}
+
+impl<'a> Iterator for OverlayLines<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut line = String::new();
+        for c in self.chars.by_ref() {
+            if c == '\n' {
+                return Some(line);
+            }
+            line.push(c);
+        }
+        self.done = true;
+        Some(line)
+    }
+}
+
+#[test]
+fn test_overlay_chars() {
+    let rope = Rope::new("let x = 1;");
+    let mut overlay = Overlay::new(&rope);
+    overlay.insert(5, ": i32");
+    overlay.insert(0, "// header\n");
+
+    assert_eq!(
+        overlay.chars().collect::<String>(),
+        "// header\nlet x: i32 = 1;"
+    );
+    assert_eq!(rope.chars().collect::<String>(), "let x = 1;");
+    assert_eq!(overlay.len(), 25);
+}
+
+#[test]
+fn test_overlay_lines() {
+    let rope = Rope::new("a\nbc");
+    let mut overlay = Overlay::new(&rope);
+    overlay.insert(4, " <- end");
+
+    let lines: Vec<String> = overlay.lines().collect();
+    assert_eq!(lines, vec!["a", "bc <- end"]);
+    assert_eq!(overlay.width(), 9);
+}
+
+#[test]
+fn test_overlay_offset_mapping() {
+    let rope = Rope::new("let x = 1;");
+    let mut overlay = Overlay::new(&rope);
+    overlay.insert(5, ": i32");
+
+    assert_eq!(overlay.to_overlay(4), 4);
+    assert_eq!(overlay.to_overlay(5), 10);
+    assert_eq!(overlay.to_base(4), Some(4));
+    assert_eq!(overlay.to_base(5), None);
+    assert_eq!(overlay.to_base(9), None);
+    assert_eq!(overlay.to_base(10), Some(5));
+}
@@ -0,0 +1,192 @@
+use std::ops::Range;
+
+use crate::buffer::TextBuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Original,
+    Add,
+}
+
+// a run of text in one of the two buffers; start and len are in bytes
+#[derive(Debug, Clone, Copy)]
+struct Piece {
+    source: Source,
+    start: usize,
+    len: usize,
+    chars: usize,
+}
+
+/// A piece table: the original text is never modified, inserted text is
+/// appended to an add buffer and the document is described by a list of
+/// pieces pointing into either buffer. Cheap for append-heavy workloads.
+#[derive(Debug, Default)]
+pub struct PieceTable {
+    original: String,
+    add: String,
+    pieces: Vec<Piece>,
+}
+
+impl PieceTable {
+    pub fn new(s: &str) -> PieceTable {
+        let mut table = PieceTable {
+            original: s.to_string(),
+            add: String::new(),
+            pieces: Vec::new(),
+        };
+        if !s.is_empty() {
+            table.pieces.push(Piece {
+                source: Source::Original,
+                start: 0,
+                len: s.len(),
+                chars: s.chars().count(),
+            });
+        }
+        table
+    }
+
+    fn text(&self, piece: &Piece) -> &str {
+        let buf = match piece.source {
+            Source::Original => &self.original,
+            Source::Add => &self.add,
+        };
+        &buf[piece.start..piece.start + piece.len]
+    }
+
+    // makes sure a piece boundary falls on char offset `at` and returns the
+    // index of the piece starting there
+    fn split_at(&mut self, at: usize) -> usize {
+        let mut pos = 0;
+        for i in 0..self.pieces.len() {
+            let piece = self.pieces[i];
+            if at == pos {
+                return i;
+            }
+            if at < pos + piece.chars {
+                let chars = at - pos;
+                let byte = self
+                    .text(&piece)
+                    .char_indices()
+                    .nth(chars)
+                    .map(|(b, _)| b)
+                    .expect("offset inside the piece");
+                self.pieces[i] = Piece {
+                    len: byte,
+                    chars,
+                    ..piece
+                };
+                self.pieces.insert(
+                    i + 1,
+                    Piece {
+                        start: piece.start + byte,
+                        len: piece.len - byte,
+                        chars: piece.chars - chars,
+                        ..piece
+                    },
+                );
+                return i + 1;
+            }
+            pos += piece.chars;
+        }
+        self.pieces.len()
+    }
+}
+
+impl TextBuffer for PieceTable {
+    fn len_chars(&self) -> usize {
+        self.pieces.iter().map(|p| p.chars).sum()
+    }
+
+    /// Panics if `offset` is past the end of the text.
+    fn insert_str(&mut self, offset: usize, s: &str) {
+        assert!(offset <= self.len_chars(), "offset out of bounds");
+        if s.is_empty() {
+            return;
+        }
+        let i = self.split_at(offset);
+        let chars = s.chars().count();
+
+        // typing at the end of the last insertion just grows that piece
+        if i > 0 {
+            let prev = &mut self.pieces[i - 1];
+            if prev.source == Source::Add && prev.start + prev.len == self.add.len() {
+                prev.len += s.len();
+                prev.chars += chars;
+                self.add.push_str(s);
+                return;
+            }
+        }
+
+        self.pieces.insert(
+            i,
+            Piece {
+                source: Source::Add,
+                start: self.add.len(),
+                len: s.len(),
+                chars,
+            },
+        );
+        self.add.push_str(s);
+    }
+
+    /// Panics if the range is past the end of the text.
+    fn remove_range(&mut self, range: Range<usize>) {
+        assert!(range.end <= self.len_chars(), "range out of bounds");
+        if range.start >= range.end {
+            return;
+        }
+        let start = self.split_at(range.start);
+        let end = self.split_at(range.end);
+        self.pieces.drain(start..end);
+    }
+
+    fn slice_to_string(&self, range: Range<usize>) -> String {
+        let mut out = String::new();
+        let mut pos = 0;
+        for piece in self.pieces.iter() {
+            if pos >= range.end {
+                break;
+            }
+            let text = self.text(piece);
+            let skip = range.start.saturating_sub(pos);
+            let take = range.end.saturating_sub(pos.max(range.start));
+            out.extend(text.chars().skip(skip).take(take));
+            pos += piece.chars;
+        }
+        out
+    }
+}
+
+#[test]
+fn test_piece_table_edits() {
+    let mut table = PieceTable::new("Hello, World!");
+    table.insert_str(6, " Cruel");
+    table.remove_range(0..1);
+    table.insert_str(0, "J");
+
+    assert_eq!(table.contents(), "Jello, Cruel World!");
+    assert_eq!(table.len_chars(), 19);
+    assert_eq!(table.slice_to_string(7..12), "Cruel");
+}
+
+#[test]
+fn test_piece_table_reversed_slice() {
+    // empty, as in `GapBuffer`
+    let table = PieceTable::new("abcdef");
+    assert_eq!(table.slice_to_string(Range { start: 5, end: 3 }), "");
+}
+
+#[test]
+fn test_piece_table_append() {
+    let mut table = PieceTable::new("");
+    for c in "grüße".chars() {
+        let len = table.len_chars();
+        table.insert_str(len, &c.to_string());
+    }
+
+    assert_eq!(table.contents(), "grüße");
+    assert_eq!(table.pieces.len(), 1);
+
+    table.remove_range(2..4);
+    assert_eq!(table.contents(), "gre");
+}
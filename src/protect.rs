@@ -0,0 +1,171 @@
+use std::ops::{Range, RangeBounds};
+
+use crate::error::RopeError;
+use crate::rope::Rope;
+use crate::stats::EditInfo;
+
+/// A rope with read-only regions, such as the prompt of a REPL or the
+/// output cells of a notebook. Edits touching a protected region are
+/// rejected; the others move the regions along with the text.
+///
+/// Regions are byte ranges. Text can be inserted right before or after a
+/// region, but not inside it.
+#[derive(Debug, Clone)]
+pub struct ProtectedRope {
+    rope: Rope,
+    // sorted and disjoint
+    regions: Vec<Range<usize>>,
+}
+
+impl ProtectedRope {
+    pub fn new(rope: Rope) -> ProtectedRope {
+        ProtectedRope {
+            rope,
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
+    pub fn into_inner(self) -> Rope {
+        self.rope
+    }
+
+    /// The protected regions, in order.
+    pub fn protected(&self) -> &[Range<usize>] {
+        &self.regions
+    }
+
+    /// Makes the bytes in `range` read-only. Overlapping or adjacent regions
+    /// are merged.
+    pub fn protect<R: RangeBounds<usize>>(&mut self, range: R) -> Result<(), RopeError> {
+        let mut range = self.rope.try_byte_range(range)?;
+        if range.is_empty() {
+            return Ok(());
+        }
+        self.regions.retain(|r| {
+            let apart = r.end < range.start || r.start > range.end;
+            if !apart {
+                range = range.start.min(r.start)..range.end.max(r.end);
+            }
+            apart
+        });
+        let at = self.regions.partition_point(|r| r.start < range.start);
+        self.regions.insert(at, range);
+        Ok(())
+    }
+
+    /// Makes the bytes in `range` editable again, splitting the regions it
+    /// cuts through.
+    pub fn unprotect(&mut self, range: Range<usize>) {
+        let regions = std::mem::take(&mut self.regions);
+        for r in regions {
+            let before = r.start..r.end.min(range.start);
+            let after = r.start.max(range.end)..r.end;
+            for part in [before, after] {
+                if !part.is_empty() {
+                    self.regions.push(part);
+                }
+            }
+        }
+    }
+
+    /// Whether an edit of `range` would touch a protected region.
+    pub fn is_protected(&self, range: Range<usize>) -> bool {
+        self.blocking(&range).is_some()
+    }
+
+    /// Replaces the bytes in `range` with `s` like `Rope::edit`, unless the
+    /// range is bad or touches a protected region.
+    pub fn edit<R: RangeBounds<usize>>(
+        &mut self,
+        range: R,
+        s: &str,
+    ) -> Result<EditInfo, RopeError> {
+        let range = self.rope.try_byte_range(range)?;
+        if let Some(region) = self.blocking(&range) {
+            return Err(RopeError::Protected {
+                start: region.start,
+                end: region.end,
+            });
+        }
+        let info = self.rope.edit(range, s);
+        for region in self.regions.iter_mut() {
+            if region.start >= info.range.end {
+                region.start = region.start - info.bytes_removed + info.bytes_added;
+                region.end = region.end - info.bytes_removed + info.bytes_added;
+            }
+        }
+        Ok(info)
+    }
+
+    /// Inserts `s` at byte offset `offset`, unless it falls inside a
+    /// protected region.
+    pub fn insert(&mut self, offset: usize, s: &str) -> Result<EditInfo, RopeError> {
+        self.edit(offset..offset, s)
+    }
+
+    /// Removes the bytes in `range`, unless it touches a protected region.
+    pub fn remove<R: RangeBounds<usize>>(&mut self, range: R) -> Result<EditInfo, RopeError> {
+        self.edit(range, "")
+    }
+
+    // the first region an edit of `range` would change
+    fn blocking(&self, range: &Range<usize>) -> Option<&Range<usize>> {
+        self.regions.iter().find(|r| match range.is_empty() {
+            // inserting at either edge leaves the region as it is
+            true => r.start < range.start && range.start < r.end,
+            false => r.start < range.end && range.start < r.end,
+        })
+    }
+}
+
+impl From<Rope> for ProtectedRope {
+    fn from(rope: Rope) -> ProtectedRope {
+        ProtectedRope::new(rope)
+    }
+}
+
+#[test]
+fn test_protected_rope() {
+    let mut doc = ProtectedRope::new(Rope::new(">>> print(1)\n1\n>>> "));
+    doc.protect(0..4).unwrap();
+    doc.protect(13..19).unwrap();
+    assert_eq!(doc.protected(), &[0..4, 13..19]);
+
+    assert_eq!(
+        doc.insert(2, "x"),
+        Err(RopeError::Protected { start: 0, end: 4 })
+    );
+    assert_eq!(
+        doc.remove(10..15),
+        Err(RopeError::Protected { start: 13, end: 19 })
+    );
+    assert!(doc.is_protected(3..5));
+    assert!(!doc.is_protected(4..4));
+
+    // edits between the regions move the later one
+    doc.edit(10..11, "42").unwrap();
+    doc.insert(20, "x = 2").unwrap();
+    assert_eq!(doc.rope().to_string(), ">>> print(42)\n1\n>>> x = 2");
+    assert_eq!(doc.protected(), &[0..4, 14..20]);
+    assert_eq!(
+        doc.remove(0..1),
+        Err(RopeError::Protected { start: 0, end: 4 })
+    );
+
+    doc.unprotect(2..16);
+    assert_eq!(doc.protected(), &[0..2, 16..20]);
+    doc.remove(2..4).unwrap();
+    doc.protect(1..3).unwrap();
+    assert_eq!(doc.protected(), &[0..3, 14..18]);
+    assert_eq!(
+        doc.insert(99, "x"),
+        Err(RopeError::OutOfBounds {
+            offset: 99,
+            len: doc.rope().len()
+        })
+    );
+}
@@ -0,0 +1,45 @@
+use crate::rope::Rope;
+
+impl Rope {
+    /// Keeps only the chars for which `keep` returns true, like
+    /// `String::retain`. Leaves that keep all their chars are shared with the
+    /// previous version rather than copied.
+    pub fn retain<F: FnMut(char) -> bool>(&mut self, mut keep: F) {
+        *self = self.retained(&mut keep);
+    }
+
+    fn retained(&self, keep: &mut dyn FnMut(char) -> bool) -> Rope {
+        match self {
+            Rope::Leaf(leaf) => {
+                let s = leaf.as_str();
+                let kept: String = s.chars().filter(|&c| keep(c)).collect();
+                if kept.len() == s.len() {
+                    return self.share();
+                }
+                Rope::chunked(&kept)
+            }
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let right = node.right.as_ref().expect("right child cannot be empty");
+                let left = left.retained(keep);
+                Rope::concat(left, right.retained(keep))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_retain() {
+    // too long to be merged with its neighbours
+    let middle = "cdef".repeat(300);
+    let mut rope = Rope::from_leaves(vec![Rope::new("a1b2"), Rope::new(&middle), Rope::new("3g")]);
+    let untouched = rope.chunks().nth(1).unwrap().as_ptr();
+
+    rope.retain(|c| !c.is_ascii_digit());
+    assert_eq!(rope.chars().collect::<String>(), format!("ab{}g", middle));
+    assert_eq!(rope.len_chars(), middle.len() + 3);
+    assert!(rope.chunks().any(|c| c.as_ptr() == untouched));
+
+    rope.retain(|_| false);
+    assert!(rope.is_empty());
+}
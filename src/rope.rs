@@ -1,51 +1,126 @@
-use std::rc::Rc;
+use std::fmt;
+use std::ops::{Range, RangeBounds};
+use std::sync::Arc;
+
+use crate::balance::MAX_DEPTH;
+use crate::builder::RopeBuilder;
+use crate::compact::MIN_VIEWED;
+use crate::count;
+use crate::flags::Flags;
+use crate::len::Len;
+use crate::long_lines::LineWidths;
+use crate::slice::RopeSlice;
+
+// upper bound for the size in bytes of leaves built from larger input
+pub(crate) const MAX_LEAF_LEN: usize = 1024;
 
 #[derive(Debug)]
 pub struct Node {
-    weight: usize,
-    left: Option<Box<Rope>>,
-    right: Option<Box<Rope>>,
+    // byte length of the left subtree
+    pub(crate) weight: Len,
+    pub(crate) bytes: Len,
+    pub(crate) chars: Len,
+    pub(crate) flags: Flags,
+    pub(crate) newlines: usize,
+    pub(crate) utf16: usize,
+    pub(crate) widths: LineWidths,
+    pub(crate) depth: usize,
+    pub(crate) left: Option<Arc<Rope>>,
+    pub(crate) right: Option<Arc<Rope>>,
+}
+
+// children are taken apart with an explicit stack, a recursive drop would
+// overflow on a degenerate tree
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        stack.extend(self.left.take());
+        stack.extend(self.right.take());
+        while let Some(child) = stack.pop() {
+            if let Ok(Rope::Node(mut node)) = Arc::try_unwrap(child) {
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Leaf {
-    buf: Rc<String>,
-    start: usize,
-    end: usize,
+    pub(crate) buf: Arc<String>,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) chars: Len,
+    pub(crate) flags: Flags,
+    pub(crate) newlines: usize,
+    pub(crate) utf16: usize,
+    pub(crate) widths: LineWidths,
 }
 
 impl Leaf {
     fn new(s: &str) -> Leaf {
-        let leaf = Leaf {
-            buf: Rc::new(s.clone().to_string()),
+        Leaf {
+            buf: Arc::new(s.to_string()),
             start: 0,
-            end: s.len() - 1,
-        };
-        leaf
+            end: s.len(),
+            chars: Len::new(count::chars(s)),
+            flags: Flags::of(s),
+            newlines: count::newlines(s),
+            utf16: s.chars().map(char::len_utf16).sum(),
+            widths: LineWidths::of(s),
+        }
     }
 
-    fn weight(&self) -> usize {
-        return self.end - self.start + 1;
+    // a leaf over text already known to be ASCII, which skips counting chars
+    // and UTF-16 units
+    pub(crate) fn ascii(s: &str) -> Leaf {
+        debug_assert!(s.is_ascii());
+        Leaf {
+            buf: Arc::new(s.to_string()),
+            start: 0,
+            end: s.len(),
+            chars: Len::new(s.len()),
+            flags: Flags::of(s),
+            newlines: count::newlines(s),
+            utf16: s.len(),
+            widths: LineWidths::of(s),
+        }
     }
 
-    fn split(&self, offset: usize) -> (Leaf, Leaf) {
-        if offset == 0 {
-            return (Leaf::new(""), Leaf::new(&self.buf.as_ref().clone()));
+    // a leaf viewing `start..end` of an existing buffer
+    pub(crate) fn view(buf: Arc<String>, start: usize, end: usize) -> Leaf {
+        let s = &buf[start..end];
+        Leaf {
+            chars: Len::new(count::chars(s)),
+            flags: Flags::of(s),
+            newlines: count::newlines(s),
+            utf16: s.chars().map(char::len_utf16).sum(),
+            widths: LineWidths::of(s),
+            buf,
+            start,
+            end,
         }
+    }
 
-        if offset >= self.weight() {
-            return (Leaf::new(&self.buf.as_ref().clone()), Leaf::new(""));
-        }
+    fn weight(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.buf[self.start..self.end]
+    }
 
-        let (left, right) = self.buf.split_at(self.start + offset);
-        ((Leaf::new(left)), Leaf::new(right))
+    // both halves view the same buffer, unless one ends up viewing so little
+    // of it that it is better off with a copy
+    fn split(&self, offset: usize) -> (Leaf, Leaf) {
+        let mid = self.start + offset.min(self.weight());
+        let left = Leaf::view(Arc::clone(&self.buf), self.start, mid);
+        let right = Leaf::view(Arc::clone(&self.buf), mid, self.end);
+        (left.compacted(MIN_VIEWED), right.compacted(MIN_VIEWED))
     }
 
     fn report(&self, start: usize, end: usize) -> Option<String> {
-        if start >= self.start && end <= self.end {
-            return Some(self.buf[start..end + 1].to_string());
-        }
-        None
+        self.as_str().get(start..end + 1).map(str::to_string)
     }
 }
 
@@ -60,49 +135,165 @@ impl Rope {
         Rope::Leaf(Leaf::new(s))
     }
 
+    /// A rope holding no text, to start a buffer from nothing.
+    pub fn empty() -> Rope {
+        Rope::new("")
+    }
+
+    #[cfg(test)]
     fn buf(&self) -> Option<&str> {
         match self {
             Rope::Node(_) => None,
-            Rope::Leaf(leaf) => Some(&leaf.buf),
+            Rope::Leaf(leaf) => Some(leaf.as_str()),
         }
     }
 
+    /// The char at char offset `i`.
     pub fn index(&self, i: usize) -> Option<char> {
         match self {
-            Rope::Leaf(leaf) => return leaf.buf.chars().nth(i),
+            Rope::Leaf(leaf) => leaf.as_str().chars().nth(i),
             Rope::Node(node) => {
-                if i <= node.weight {
-                    return node.left.as_ref()?.index(i);
+                let left = node.left.as_ref()?;
+                if i < left.len_chars() {
+                    return left.index(i);
                 }
-                node.right.as_ref()?.index(i - node.weight)
+                node.right.as_ref()?.index(i - left.len_chars())
             }
         }
     }
 
-    // weight of the RopeNode is weight of Node or bufLen of Leaf
-    fn weight(&self) -> usize {
+    /// Length of the rope in bytes, the unit `insert`, `delete` and `report`
+    /// take their offsets in. Cached per node, so this is O(1).
+    pub fn len(&self) -> usize {
+        match self {
+            Rope::Leaf(leaf) => leaf.weight(),
+            Rope::Node(node) => node.bytes.get(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Same as `len`.
+    pub fn len_bytes(&self) -> usize {
+        self.len()
+    }
+
+    /// Length of the rope in chars. Cached per node, so this is O(1).
+    pub fn len_chars(&self) -> usize {
+        match self {
+            Rope::Leaf(leaf) => leaf.chars.get(),
+            Rope::Node(node) => node.chars.get(),
+        }
+    }
+
+    // number of line breaks in the rope, cached per node
+    pub(crate) fn newlines(&self) -> usize {
+        match self {
+            Rope::Leaf(leaf) => leaf.newlines,
+            Rope::Node(node) => node.newlines,
+        }
+    }
+
+    // line lengths at the edges and the longest line, cached per node
+    pub(crate) fn widths(&self) -> LineWidths {
         match self {
-            Rope::Leaf(leaf) => return leaf.weight(),
-            Rope::Node(node) => return node.weight,
+            Rope::Leaf(leaf) => leaf.widths,
+            Rope::Node(node) => node.widths,
+        }
+    }
+
+    /// Whether byte offset `offset` is the start or end of a char. Offsets
+    /// past the end are not.
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        if offset == 0 || offset == self.len() {
+            return true;
+        }
+        if offset > self.len() {
+            return false;
+        }
+        let (mut chunks, at) = self.chunks_at_byte(offset);
+        chunks
+            .next()
+            .is_some_and(|chunk| chunk.is_char_boundary(at))
+    }
+
+    /// The byte at byte offset `offset`, in O(log n).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not less than the length in bytes.
+    pub fn byte(&self, offset: usize) -> u8 {
+        assert!(
+            offset < self.len(),
+            "byte offset {} out of bounds for length {}",
+            offset,
+            self.len()
+        );
+        let (mut chunks, at) = self.chunks_at_byte(offset);
+        chunks.next().expect("offset within the rope").as_bytes()[at]
+    }
+
+    /// Converts a char offset to a byte offset. Offsets past the end are
+    /// clamped to the length of the rope in bytes.
+    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+        if self.is_ascii() {
+            return char_idx.min(self.len());
+        }
+        match self {
+            Rope::Leaf(leaf) => {
+                let s = leaf.as_str();
+                s.char_indices().nth(char_idx).map_or(s.len(), |(b, _)| b)
+            }
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let left_chars = left.len_chars();
+                if char_idx < left_chars {
+                    return left.char_to_byte(char_idx);
+                }
+                node.weight.get()
+                    + node
+                        .right
+                        .as_ref()
+                        .map_or(0, |r| r.char_to_byte(char_idx - left_chars))
+            }
         }
     }
 
-    // length of the RopeNode is either the weight of the node if its leaf
-    // or its weight (left child weight) + length of its right node
-    fn length(&self) -> usize {
+    /// Converts a byte offset to the offset of the char containing it.
+    /// Offsets past the end are clamped to the length of the rope in chars.
+    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
+        if self.is_ascii() {
+            return byte_idx.min(self.len());
+        }
         match self {
-            Rope::Leaf(leaf) => return leaf.weight(),
+            Rope::Leaf(leaf) => {
+                let s = leaf.as_str();
+                if byte_idx >= s.len() {
+                    return leaf.chars.get();
+                }
+                s.char_indices()
+                    .skip(1)
+                    .take_while(|(b, _)| *b <= byte_idx)
+                    .count()
+            }
             Rope::Node(node) => {
-                return node.weight
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                if byte_idx < node.weight.get() {
+                    return left.byte_to_char(byte_idx);
+                }
+                let rest = Len::new(byte_idx) - node.weight;
+                left.len_chars()
                     + node
                         .right
                         .as_ref()
-                        .expect("right node cannot be None")
-                        .length()
+                        .map_or(0, |r| r.byte_to_char(rest.get()))
             }
         }
     }
 
+    #[cfg(test)]
     fn is_leaf(&self) -> bool {
         match self {
             Rope::Leaf(_) => true,
@@ -110,92 +301,402 @@ impl Rope {
         }
     }
 
-    fn is_node(&self) -> bool {
+    pub(crate) fn join(left: Arc<Rope>, right: Arc<Rope>) -> Rope {
+        // splits at 0 or len produce empty halves, don't keep them around
+        if left.is_empty() {
+            return right.share();
+        }
+        if right.is_empty() {
+            return left.share();
+        }
+        let len = left.len().checked_add(right.len());
+        assert!(len.is_some_and(|len| len <= Rope::MAX_LEN), "rope too long");
+        let joined = Rope::Node(Node {
+            weight: Len::new(left.len()),
+            bytes: Len::new(left.len()) + Len::new(right.len()),
+            chars: Len::new(left.len_chars()) + Len::new(right.len_chars()),
+            flags: Flags::join(left.flags(), right.flags()),
+            newlines: left.newlines() + right.newlines(),
+            utf16: left.len_utf16() + right.len_utf16(),
+            widths: LineWidths::join(left.widths(), right.widths()),
+            depth: 1 + left.depth().max(right.depth()),
+            left: Some(left),
+            right: Some(right),
+        });
+        // every node is built here, so no path can grow the tree past the
+        // limit; rebalancing yields a tree of depth log2 of the leaf count
+        if joined.depth() > MAX_DEPTH {
+            return joined.rebalance();
+        }
+        joined
+    }
+
+    /// Concatenates two ropes, sharing their subtrees. Two leaves small enough
+    /// to fit one are merged into a single leaf.
+    pub fn concat(left: Rope, right: Rope) -> Rope {
+        if let (Rope::Leaf(l), Rope::Leaf(r)) = (&left, &right) {
+            if l.weight() + r.weight() <= MAX_LEAF_LEN && l.weight() > 0 && r.weight() > 0 {
+                return Rope::new(&(l.as_str().to_string() + r.as_str()));
+            }
+        }
+        Rope::join(Arc::new(left), Arc::new(right))
+    }
+
+    // a new handle on the same content: children and leaf buffers are shared,
+    // so this is O(1)
+    pub(crate) fn share(&self) -> Rope {
         match self {
-            Rope::Leaf(_) => false,
-            Rope::Node(_) => true,
+            Rope::Leaf(leaf) => Rope::Leaf(Leaf {
+                buf: Arc::clone(&leaf.buf),
+                start: leaf.start,
+                end: leaf.end,
+                chars: leaf.chars,
+                flags: leaf.flags,
+                newlines: leaf.newlines,
+                utf16: leaf.utf16,
+                widths: leaf.widths,
+            }),
+            Rope::Node(node) => Rope::Node(Node {
+                weight: node.weight,
+                bytes: node.bytes,
+                chars: node.chars,
+                flags: node.flags,
+                newlines: node.newlines,
+                utf16: node.utf16,
+                widths: node.widths,
+                depth: node.depth,
+                left: node.left.clone(),
+                right: node.right.clone(),
+            }),
         }
     }
 
-    fn join(left: Box<Rope>, right: Box<Rope>) -> Rope {
-        Rope::Node(Node {
-            weight: left.length(),
-            left: Some(left),
-            right: Some(right),
-        })
+    // builds a balanced rope out of `s` with leaves of at most MAX_LEAF_LEN
+    // bytes, so large pastes don't end up in one pathological leaf
+    pub(crate) fn chunked(s: &str) -> Rope {
+        if s.len() <= MAX_LEAF_LEN {
+            return Rope::new(s);
+        }
+        let mut builder = RopeBuilder::new();
+        builder.append(s);
+        builder.finish()
+    }
+
+    // builds a balanced tree over the given ropes, keeping their order
+    pub(crate) fn from_leaves(mut leaves: Vec<Rope>) -> Rope {
+        if leaves.is_empty() {
+            return Rope::empty();
+        }
+        while leaves.len() > 1 {
+            let mut parents = Vec::with_capacity(leaves.len().div_ceil(2));
+            let mut iter = leaves.into_iter();
+            while let Some(left) = iter.next() {
+                match iter.next() {
+                    Some(right) => parents.push(Rope::join(Arc::new(left), Arc::new(right))),
+                    None => parents.push(left),
+                }
+            }
+            leaves = parents;
+        }
+        leaves.pop().expect("at least one leaf")
     }
 
-    fn split(&mut self, offset: usize) -> (Rope, Rope) {
+    // splits into two ropes holding [0, offset) and [offset, len). Subtrees
+    // that lie entirely on one side are shared, self is left untouched.
+    pub(crate) fn split(&self, offset: usize) -> (Rope, Rope) {
         match self {
             Rope::Leaf(leaf) => {
                 let (l, r) = leaf.split(offset);
-                return (Rope::Leaf(l), Rope::Leaf(r));
+                (Rope::Leaf(l), Rope::Leaf(r))
             }
             Rope::Node(node) => {
-                let w = node.weight;
+                let w = node.weight.get();
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let right = node.right.as_ref().expect("right child cannot be empty");
 
                 // < not <= because w - always length of the string (offset -1)
                 if offset < w {
-                    let (l, r) = node
-                        .left
-                        .as_mut()
-                        .expect("left child cannot be empty")
-                        .split(offset);
-                    let r = Rope::join(
-                        Box::new(r),
-                        node.right.take().expect("right child cannot be empty"),
-                    );
-                    return (l, r);
+                    let (l, r) = left.split(offset);
+                    return (l, Rope::join(Arc::new(r), Arc::clone(right)));
                 }
 
-                let (l, r) = node
-                    .right
-                    .as_mut()
-                    .expect("right child cannot be empty")
-                    .split(offset - w);
-                let l = Rope::join(
-                    Box::new(l),
-                    node.right.take().expect("left child cannot be empty"),
-                );
-                return (l, r);
+                let (l, r) = right.split((Len::new(offset) - node.weight).get());
+                (Rope::join(Arc::clone(left), Arc::new(l)), r)
             }
         }
     }
 
-    pub fn insert(&mut self, s: &str, offset: usize) -> Rope {
+    /// Inserts `s` at byte offset `offset`. Other handles on the rope, such
+    /// as the ones made by `share`, are left intact and keep sharing all
+    /// untouched subtrees with it.
+    pub fn insert(&mut self, offset: usize, s: &str) {
         let (l, r) = self.split(offset);
 
-        let leaf = Rope::new(s);
+        let leaf = Rope::chunked(s);
 
-        let tmp = Rope::join(Box::new(l), Box::new(leaf));
-        let res = Rope::join(Box::new(tmp), Box::new(r));
-        return res;
+        *self = Rope::concat(Rope::concat(l, leaf), r);
     }
 
-    pub fn delete(&mut self, start: usize, end: usize) -> Rope {
-        let (l, mut r) = self.split(start);
+    /// Removes the inclusive byte range `start..=end`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `end` comes before `start`, or `start` is past the end of
+    /// the rope.
+    pub fn delete(&mut self, start: usize, end: usize) {
+        assert!(
+            start <= end,
+            "range starts at {} but ends at {}",
+            start,
+            end
+        );
+        let (l, r) = self.split(start);
 
-        let (_, r2) = r.split(end - start + 1);
+        let (_, r2) = r.split((Len::new(end) - Len::new(start) + Len::new(1)).get());
 
-        Rope::join(Box::new(l), Box::new(r2))
+        *self = Rope::concat(l, r2);
     }
 
-    pub fn report(&self, start: usize, end: usize) -> Option<String> {
+    /// Removes the bytes in `range`, which takes the same forms as the range
+    /// of `String::drain`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range starts after it ends or ends past the end of the
+    /// rope.
+    pub fn remove<R: RangeBounds<usize>>(&mut self, range: R) {
+        let range = self.byte_range(range);
+
+        let (l, r) = self.split(range.start);
+        let (_, r) = r.split(range.len());
+        *self = Rope::concat(l, r);
+    }
+
+    /// Removes the bytes in `range` and yields the removed chars, like
+    /// `String::drain`. Unlike it the text is removed right away rather than
+    /// when the iterator is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the same ranges as `remove`.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain {
+        let range = self.byte_range(range);
+
+        let (l, r) = self.split(range.start);
+        let (removed, r) = r.split(range.len());
+        *self = Rope::concat(l, r);
+        Drain(removed.into_iter())
+    }
+
+    /// Replaces the bytes in `range` with `s`.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the same ranges as `remove`.
+    pub fn replace_range<R: RangeBounds<usize>>(&mut self, range: R, s: &str) {
+        let range = self.byte_range(range);
+
+        let (l, r) = self.split(range.start);
+        let (_, r) = r.split(range.len());
+        *self = Rope::concat(Rope::concat(l, Rope::chunked(s)), r);
+    }
+
+    /// Splits the rope at byte offset `at`, keeping `[0, at)` in self and
+    /// returning `[at, len)`, like `String::split_off`. Both halves share the
+    /// untouched subtrees with the original.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end of the rope.
+    pub fn split_off(&mut self, at: usize) -> Rope {
+        assert!(at <= self.len(), "split offset {} out of bounds", at);
+        let (l, r) = self.split(at);
+        *self = l;
+        r
+    }
+
+    /// Shortens the rope to its first `new_len` bytes, like
+    /// `String::truncate`. Only the path down to the cut is rebuilt, so this
+    /// is O(log n). Does nothing if `new_len` is not below the length.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            *self = self.prefix(new_len);
+        }
+    }
+
+    /// Removes all the text, dropping this handle's hold on its leaves.
+    pub fn clear(&mut self) {
+        *self = Rope::empty();
+    }
+
+    // the left half of split(offset), without building the right one
+    fn prefix(&self, offset: usize) -> Rope {
         match self {
             Rope::Leaf(leaf) => {
-                leaf.report(start, end)
+                let end = leaf.start + offset.min(leaf.weight());
+                Rope::Leaf(Leaf::view(Arc::clone(&leaf.buf), leaf.start, end).compacted(MIN_VIEWED))
             }
             Rope::Node(node) => {
-                let len = end - start + 1;
+                let w = node.weight.get();
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let right = node.right.as_ref().expect("right child cannot be empty");
+
+                if offset <= w {
+                    return left.prefix(offset);
+                }
+                let rest = right.prefix((Len::new(offset) - node.weight).get());
+                Rope::join(Arc::clone(left), Arc::new(rest))
+            }
+        }
+    }
+
+    // resolves `range` against the length of the rope, panicking when out of
+    // bounds
+    pub(crate) fn byte_range<R: RangeBounds<usize>>(&self, range: R) -> Range<usize> {
+        self.try_byte_range(range)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns a new rope with `slice` spliced in at char offset `offset`.
+    /// Unlike `insert` the spliced text is not flattened into a new leaf, so
+    /// duplicating a selection shares its leaves with the source rope.
+    pub fn insert_slice(&self, offset: usize, slice: RopeSlice<'_>) -> Rope {
+        let (l, r) = self.split(self.char_to_byte(offset));
+
+        Rope::concat(Rope::concat(l, slice.to_rope()), r)
+    }
+
+    /// The bytes `start..=end` as a string, or none when the range does not
+    /// fit. Prefer `slice_to_string`, which takes ordinary ranges.
+    pub fn report(&self, start: usize, end: usize) -> Option<String> {
+        match self {
+            Rope::Leaf(leaf) => leaf.report(start, end),
+            Rope::Node(node) => {
+                let len = Len::new(end) - Len::new(start) + Len::new(1);
                 if len <= node.weight {
                     return node.left.as_ref()?.report(start, end);
                 }
-                let l = node.left.as_ref()?.report(start, node.weight - 1)?;
-                let r = node.right.as_ref()?.report(0, len - node.weight - 1)?;
+                let one = Len::new(1);
+                let l = node
+                    .left
+                    .as_ref()?
+                    .report(start, (node.weight - one).get())?;
+                let r = node
+                    .right
+                    .as_ref()?
+                    .report(0, (len - node.weight - one).get())?;
                 Some(l + &r)
             }
         }
     }
+
+    /// Iterates over the leaf strings of the rope from left to right.
+    pub fn chunks(&self) -> Chunks<'_> {
+        Chunks { stack: vec![self] }
+    }
+
+    /// Iterates over the chars of the rope without consuming it.
+    pub fn chars(&self) -> Chars<'_> {
+        Chars {
+            chunks: self.chunks(),
+            cur: "".chars(),
+        }
+    }
+
+    // chunks starting with the one holding `byte_idx`, along with the
+    // offset of `byte_idx` in it
+    pub(crate) fn chunks_at_byte(&self, mut byte_idx: usize) -> (Chunks<'_>, usize) {
+        let mut stack = Vec::new();
+        let mut rope = self;
+        while let Rope::Node(node) = rope {
+            let left = node.left.as_ref().expect("left child cannot be empty");
+            let right = node.right.as_ref().expect("right child cannot be empty");
+            if byte_idx < node.weight.get() {
+                stack.push(right.as_ref());
+                rope = left;
+            } else {
+                byte_idx -= node.weight.get();
+                rope = right;
+            }
+        }
+        stack.push(rope);
+        (Chunks { stack }, byte_idx)
+    }
+
+    /// Iterates over the leaf strings right to left, starting with the part
+    /// of the leaf that ends at byte offset `byte_idx`, clamped to the rope.
+    /// For scanning backwards from a cursor without walking from the start.
+    pub fn chunks_rev_at(&self, byte_idx: usize) -> ChunksRev<'_> {
+        let mut byte_idx = byte_idx.min(self.len());
+        let mut stack = Vec::new();
+        let mut rope = self;
+        while let Rope::Node(node) = rope {
+            let left = node.left.as_ref().expect("left child cannot be empty");
+            let right = node.right.as_ref().expect("right child cannot be empty");
+            if byte_idx <= node.weight.get() {
+                rope = left;
+            } else {
+                byte_idx -= node.weight.get();
+                stack.push(left.as_ref());
+                rope = right;
+            }
+        }
+        let first = match rope {
+            Rope::Leaf(leaf) => &leaf.as_str()[..byte_idx],
+            Rope::Node(_) => unreachable!("descended to a leaf"),
+        };
+        ChunksRev {
+            first: Some(first),
+            stack,
+        }
+    }
+
+    /// Iterates over the chars of the rope starting at char offset
+    /// `char_idx`, descending to it in O(log n) instead of skipping.
+    pub fn chars_at(&self, mut char_idx: usize) -> Chars<'_> {
+        let mut stack = Vec::new();
+        let mut rope = self;
+        loop {
+            match rope {
+                Rope::Leaf(leaf) => {
+                    let s = leaf.as_str();
+                    let byte = s.char_indices().nth(char_idx).map_or(s.len(), |(b, _)| b);
+                    return Chars {
+                        chunks: Chunks { stack },
+                        cur: s[byte..].chars(),
+                    };
+                }
+                Rope::Node(node) => {
+                    let left = node.left.as_ref().expect("left child cannot be empty");
+                    let right = node.right.as_ref().expect("right child cannot be empty");
+                    let left_chars = left.len_chars();
+                    if char_idx < left_chars {
+                        stack.push(right.as_ref());
+                        rope = left;
+                    } else {
+                        char_idx -= left_chars;
+                        rope = right;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Clones in O(1): the copy shares all nodes and leaf buffers with self, and
+/// later edits to either one only rebuild the path to the edit.
+impl Clone for Rope {
+    fn clone(&self) -> Rope {
+        self.share()
+    }
+}
+
+/// Writes the leaves one after the other, without building the whole string
+/// first.
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
 }
 
 impl IntoIterator for Rope {
@@ -203,10 +704,10 @@ impl IntoIterator for Rope {
     type IntoIter = RopeIterator;
 
     fn into_iter(self) -> Self::IntoIter {
-	RopeIterator {
-	    rope: self,
-	    index: 0
-	}
+        RopeIterator {
+            rope: self,
+            index: 0,
+        }
     }
 }
 
@@ -219,16 +720,165 @@ impl Iterator for RopeIterator {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-	let res = self.rope.index(self.index);
-	self.index += 1;
-	res
+        let res = self.rope.index(self.index);
+        self.index += 1;
+        res
+    }
+}
+
+/// The chars removed by `Rope::drain`.
+pub struct Drain(RopeIterator);
+
+impl Iterator for Drain {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+pub struct Chunks<'a> {
+    stack: Vec<&'a Rope>,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(rope) = self.stack.pop() {
+            match rope {
+                Rope::Leaf(leaf) => return Some(leaf.as_str()),
+                Rope::Node(node) => {
+                    if let Some(right) = node.right.as_ref() {
+                        self.stack.push(right);
+                    }
+                    if let Some(left) = node.left.as_ref() {
+                        self.stack.push(left);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+pub struct ChunksRev<'a> {
+    first: Option<&'a str>,
+    stack: Vec<&'a Rope>,
+}
+
+impl<'a> Iterator for ChunksRev<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(first) = self.first.take() {
+            return Some(first);
+        }
+        while let Some(rope) = self.stack.pop() {
+            match rope {
+                Rope::Leaf(leaf) => return Some(leaf.as_str()),
+                Rope::Node(node) => {
+                    if let Some(left) = node.left.as_ref() {
+                        self.stack.push(left);
+                    }
+                    if let Some(right) = node.right.as_ref() {
+                        self.stack.push(right);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+pub struct Chars<'a> {
+    chunks: Chunks<'a>,
+    cur: std::str::Chars<'a>,
+}
+
+impl<'a> Iterator for Chars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.cur.next() {
+                return Some(c);
+            }
+            self.cur = self.chunks.next()?.chars();
+        }
     }
 }
 
 #[test]
 fn test_rope_new() {
     let rope = Rope::new("Hello, World!");
-    assert_eq!(rope.is_leaf(), true);
+    assert!(rope.is_leaf());
+}
+
+#[test]
+fn test_rope_empty() {
+    let rope = Rope::empty();
+    assert!(rope.is_empty());
+    assert_eq!(rope.len(), 0);
+    assert_eq!(rope.chars().count(), 0);
+    assert_eq!(rope.share().into_iter().count(), 0);
+    assert_eq!(rope.report(0, 0), None);
+
+    let (l, r) = rope.split(0);
+    assert_eq!((l.len(), r.len()), (0, 0));
+    let mut rope = rope;
+    rope.delete(0, 0);
+    assert_eq!(rope.len(), 0);
+
+    rope.insert(0, "Hello");
+    assert!(rope.is_leaf());
+    assert_eq!(rope.chars().collect::<String>(), "Hello");
+    rope.delete(0, 4);
+    assert_eq!(rope.chars().count(), 0);
+    assert_eq!(Rope::new("").chars().count(), 0);
+}
+
+#[test]
+fn test_rope_len() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+    assert_eq!(rope.len(), 15);
+    assert!(!rope.is_empty());
+
+    let half = Rope::Node(Node {
+        weight: Len::new(5),
+        bytes: Len::new(5),
+        chars: Len::new(5),
+        flags: Flags::default(),
+        newlines: 0,
+        utf16: 5,
+        widths: LineWidths::of("Hello"),
+        depth: 1,
+        left: Some(Arc::new(Rope::new("Hello"))),
+        right: None,
+    });
+    assert_eq!(half.len(), 5);
+}
+
+#[test]
+fn test_drop_degenerate_tree() {
+    // built by hand, as nothing else makes a tree this deep
+    let mut rope = Rope::new("a");
+    for depth in 1..=200_000 {
+        rope = Rope::Node(Node {
+            weight: Len::new(rope.len()),
+            bytes: Len::new(rope.len() + 1),
+            chars: Len::new(rope.len() + 1),
+            flags: Flags::default(),
+            newlines: 0,
+            utf16: rope.len() + 1,
+            widths: LineWidths::of(""),
+            depth,
+            left: Some(Arc::new(rope)),
+            right: Some(Arc::new(Rope::new("b"))),
+        });
+    }
+    assert_eq!(rope.depth(), 200_000);
+    drop(rope);
 }
 
 #[test]
@@ -238,6 +888,25 @@ fn test_rope_index() {
     assert_eq!(rope.index(0).unwrap(), 'H');
     assert_eq!(rope.index(3).unwrap(), 'l');
     assert_eq!(rope.index(12).unwrap(), '!');
+
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", Wörld!")]);
+    assert_eq!(rope.index(4), Some('e'));
+    assert_eq!(rope.index(5), Some(','));
+    assert_eq!(rope.index(8), Some('ö'));
+    assert_eq!(rope.index(13), None);
+}
+
+#[test]
+fn test_rope_len_bytes_and_chars() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", Wörld!")]);
+    assert_eq!(rope.len_bytes(), 16);
+    assert_eq!(rope.len_chars(), 13);
+
+    rope.insert(7, "日本");
+    rope.delete(0, 1);
+    assert_eq!(rope.chars().collect::<String>(), "üße日本, Wörld!");
+    assert_eq!(rope.len_bytes(), 20);
+    assert_eq!(rope.len_chars(), 13);
 }
 
 #[test]
@@ -245,7 +914,7 @@ fn test_rope_join() {
     let rope1 = Rope::new("Hello,");
     let rope2 = Rope::new(" World!");
 
-    let rope = Rope::join(Box::new(rope1), Box::new(rope2));
+    let rope = Rope::join(Arc::new(rope1), Arc::new(rope2));
 
     assert_eq!(rope.index(1).unwrap(), 'e');
     assert_eq!(rope.index(0).unwrap(), 'H');
@@ -255,7 +924,7 @@ fn test_rope_join() {
 
 #[test]
 fn test_rope_split() {
-    let mut rope = Rope::new("Hello, World!");
+    let rope = Rope::new("Hello, World!");
     let (left, right) = rope.split(5);
     assert_eq!(left.buf(), Some("Hello"));
     assert_eq!(right.buf(), Some(", World!"));
@@ -263,7 +932,7 @@ fn test_rope_split() {
 
 #[test]
 fn test_rope_report() {
-    let mut rope = Rope::new("Hello, World!");
+    let rope = Rope::new("Hello, World!");
 
     assert_eq!(rope.report(1, 5).unwrap(), "ello,");
 
@@ -277,7 +946,7 @@ fn test_rope_report() {
 fn test_rope_insert() {
     let mut rope = Rope::new("Hello, World!");
 
-    let rope = rope.insert(" Cruel", 6);
+    rope.insert(6, " Cruel");
 
     assert_eq!(rope.report(0, 18).unwrap(), "Hello, Cruel World!");
 }
@@ -285,21 +954,311 @@ fn test_rope_insert() {
 #[test]
 fn test_rope_delete() {
     let mut rope = Rope::new("Hello, World!");
-    rope = rope.delete(2, 4);
+    rope.delete(2, 4);
     assert_eq!(rope.report(0, 9).unwrap(), "He, World!");
 }
 
+#[test]
+fn test_rope_remove() {
+    let mut rope = Rope::new("Hello, World!");
+    rope.remove(5..12);
+    assert_eq!(rope.chars().collect::<String>(), "Hello!");
+
+    rope.remove(..=1);
+    assert_eq!(rope.chars().collect::<String>(), "llo!");
+    rope.remove(3..);
+    rope.remove(1..1);
+    assert_eq!(rope.chars().collect::<String>(), "llo");
+    rope.remove(..);
+    assert!(rope.is_empty());
+}
+
+#[test]
+fn test_rope_drain() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Hello, "), Rope::new("Wörld!")]);
+
+    assert_eq!(rope.drain(5..8).collect::<String>(), ", W");
+    assert_eq!(rope.chars().collect::<String>(), "Helloörld!");
+
+    // the text is gone even if the iterator is not used
+    rope.drain(..5);
+    assert_eq!(rope.drain(..).collect::<String>(), "örld!");
+    assert!(rope.is_empty());
+}
+
+#[test]
+fn test_rope_replace_range() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Hello, "), Rope::new("World!")]);
+    rope.replace_range(7..12, "Wörld");
+    assert_eq!(rope.chars().collect::<String>(), "Hello, Wörld!");
+
+    rope.replace_range(..5, "");
+    rope.replace_range(0..0, "Oh");
+    rope.replace_range(rope.len().., "!");
+    assert_eq!(rope.chars().collect::<String>(), "Oh, Wörld!!");
+}
+
+#[test]
+fn test_rope_split_off() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Hello, "), Rope::new("World!")]);
+    let copy = rope.share();
+
+    let tail = rope.split_off(5);
+    assert_eq!(rope.chars().collect::<String>(), "Hello");
+    assert_eq!(tail.chars().collect::<String>(), ", World!");
+    assert_eq!(copy.chars().collect::<String>(), "Hello, World!");
+
+    assert!(rope.split_off(5).is_empty());
+    assert_eq!(rope.split_off(0).len(), 5);
+    assert!(rope.is_empty());
+}
+
+#[test]
+fn test_rope_truncate_and_clear() {
+    let mut rope = Rope::from_leaves(vec![
+        Rope::new("Hello"),
+        Rope::new(", "),
+        Rope::new("Wörld!"),
+    ]);
+    let copy = rope.share();
+
+    rope.truncate(20);
+    assert_eq!(rope.len(), 14);
+    rope.truncate(10);
+    assert_eq!(rope.chars().collect::<String>(), "Hello, Wö");
+    assert_eq!(rope.len_chars(), 9);
+    rope.truncate(7);
+    assert_eq!(rope.chars().collect::<String>(), "Hello, ");
+    rope.truncate(5);
+    assert!(rope.is_leaf());
+    assert_eq!(copy.chars().collect::<String>(), "Hello, Wörld!");
+
+    rope.clear();
+    assert!(rope.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn test_rope_remove_out_of_bounds() {
+    let mut rope = Rope::new("Hello");
+    rope.remove(2..6);
+}
 
 #[test]
 fn test_rope_iterator() {
     let rope = Rope::new("Hello!");
     let mut itr = rope.into_iter();
 
-     assert_eq!(itr.next(), Some('H'));
-     assert_eq!(itr.next(), Some('e'));
-     assert_eq!(itr.next(), Some('l'));
-     assert_eq!(itr.next(), Some('l'));
-     assert_eq!(itr.next(), Some('o'));
-     assert_eq!(itr.next(), Some('!'));
-     assert_eq!(itr.next(), None);
+    assert_eq!(itr.next(), Some('H'));
+    assert_eq!(itr.next(), Some('e'));
+    assert_eq!(itr.next(), Some('l'));
+    assert_eq!(itr.next(), Some('l'));
+    assert_eq!(itr.next(), Some('o'));
+    assert_eq!(itr.next(), Some('!'));
+    assert_eq!(itr.next(), None);
+}
+
+#[test]
+fn test_rope_clone() {
+    let text = "abcdefgh".repeat(1024);
+    let mut builder = crate::RopeBuilder::new();
+    builder.append(&text);
+    let rope = builder.finish();
+
+    let mut copy = rope.clone();
+    assert!(copy.ptr_eq_subtree(&rope));
+    assert!(copy
+        .chunks()
+        .zip(rope.chunks())
+        .all(|(a, b)| a.as_ptr() == b.as_ptr()));
+
+    copy.insert(4000, "X");
+    assert_eq!(rope.to_string(), text);
+    assert_eq!(copy.len(), text.len() + 1);
+    // only the leaf edited was copied
+    let shared = copy
+        .chunks()
+        .filter(|c| rope.chunks().any(|o| o.as_ptr() == c.as_ptr()));
+    assert!(shared.count() >= rope.chunks().count() - 1);
+}
+
+#[test]
+fn test_rope_display() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+    assert_eq!(rope.to_string(), "Grüße, World!");
+    assert_eq!(format!("<{}>", rope.slice(7..12)), "<World>");
+}
+
+#[test]
+fn test_rope_chunks() {
+    let rope1 = Rope::new("Hello,");
+    let rope2 = Rope::new(" World!");
+    let rope = Rope::join(Arc::new(rope1), Arc::new(rope2));
+
+    let chunks: Vec<&str> = rope.chunks().collect();
+    assert_eq!(chunks, vec!["Hello,", " World!"]);
+    assert_eq!(rope.chars().collect::<String>(), "Hello, World!");
+}
+
+#[test]
+fn test_rope_chunks_rev_at() {
+    let rope = Rope::from_leaves(vec![Rope::new("ab"), Rope::new("cd"), Rope::new("ef")]);
+
+    assert_eq!(
+        rope.chunks_rev_at(5).collect::<Vec<_>>(),
+        vec!["e", "cd", "ab"]
+    );
+    assert_eq!(rope.chunks_rev_at(4).collect::<Vec<_>>(), vec!["cd", "ab"]);
+    assert_eq!(
+        rope.chunks_rev_at(99).collect::<Vec<_>>(),
+        vec!["ef", "cd", "ab"]
+    );
+    assert_eq!(rope.chunks_rev_at(0).collect::<Vec<_>>(), vec![""]);
+}
+
+#[test]
+fn test_rope_byte() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grü"), Rope::new("ße!")]);
+    let bytes: Vec<u8> = (0..rope.len()).map(|i| rope.byte(i)).collect();
+    assert_eq!(bytes, "Grüße!".as_bytes());
+}
+
+#[test]
+fn test_rope_is_ascii() {
+    let rope = Rope::join(
+        Arc::new(Rope::new("Hello, ")),
+        Arc::new(Rope::new("World!")),
+    );
+    assert!(rope.is_ascii());
+
+    let rope = Rope::join(
+        Arc::new(Rope::new("Hello, ")),
+        Arc::new(Rope::new("Wörld!")),
+    );
+    assert!(!rope.is_ascii());
+}
+
+#[test]
+fn test_rope_char_byte_conversion() {
+    let rope = Rope::join(
+        Arc::new(Rope::new("héllo, ")),
+        Arc::new(Rope::new("wörld!")),
+    );
+
+    assert_eq!(rope.char_to_byte(0), 0);
+    assert_eq!(rope.char_to_byte(2), 3);
+    assert_eq!(rope.char_to_byte(9), 11);
+    assert_eq!(rope.char_to_byte(100), 15);
+
+    assert_eq!(rope.byte_to_char(3), 2);
+    assert_eq!(rope.byte_to_char(2), 1);
+    assert_eq!(rope.byte_to_char(11), 9);
+    assert_eq!(rope.byte_to_char(9), 8);
+    assert_eq!(rope.byte_to_char(100), 13);
+}
+
+#[test]
+fn test_rope_edits_keep_original() {
+    let rope = Rope::new("Hello, World!");
+    let mut inserted = rope.share();
+    inserted.insert(6, " Cruel");
+    let mut deleted = inserted.share();
+    deleted.delete(13, 15);
+
+    assert_eq!(rope.report(0, 12).unwrap(), "Hello, World!");
+    assert_eq!(inserted.report(0, 18).unwrap(), "Hello, Cruel World!");
+    assert_eq!(deleted.report(0, 15).unwrap(), "Hello, Cruel ld!");
+
+    // splitting a node keeps both the node and the shared halves valid
+    let (left, right) = inserted.split(8);
+    assert_eq!(left.chars().collect::<String>(), "Hello, C");
+    assert_eq!(right.chars().collect::<String>(), "ruel World!");
+    assert_eq!(inserted.chars().collect::<String>(), "Hello, Cruel World!");
+}
+
+#[test]
+fn test_rope_chars_at() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("héllo"),
+        Rope::new(", "),
+        Rope::new("wörld!"),
+    ]);
+
+    assert_eq!(rope.chars_at(0).collect::<String>(), "héllo, wörld!");
+    assert_eq!(rope.chars_at(4).collect::<String>(), "o, wörld!");
+    assert_eq!(rope.chars_at(8).collect::<String>(), "örld!");
+    assert_eq!(rope.chars_at(13).next(), None);
+}
+
+#[test]
+fn test_rope_slice() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("Grüße"),
+        Rope::new(", "),
+        Rope::new("World!"),
+    ]);
+
+    assert_eq!(rope.slice(0..5).chars().collect::<String>(), "Grüße");
+    assert_eq!(rope.slice(3..9).chars().collect::<String>(), "ße, Wo");
+    assert_eq!(rope.slice(7..13).chars().collect::<String>(), "World!");
+    assert_eq!(
+        rope.slice(0..13).chars().collect::<String>(),
+        "Grüße, World!"
+    );
+}
+
+#[test]
+fn test_rope_insert_slice() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+    let other = Rope::new("Hello, Cruel World!");
+
+    let spliced = rope.insert_slice(7, other.slice(7..13));
+    assert_eq!(spliced.chars().collect::<String>(), "Grüße, Cruel World!");
+
+    let spliced = rope.insert_slice(0, other.slice(0..7));
+    assert_eq!(spliced.chars().collect::<String>(), "Hello, Grüße, World!");
+
+    let spliced = rope.insert_slice(13, other.slice(12..19));
+    assert_eq!(spliced.chars().collect::<String>(), "Grüße, World! World!");
+}
+
+#[test]
+fn test_rope_insert_large() {
+    let mut rope = Rope::new("Hello, World!");
+    let paste = "x".repeat(MAX_LEAF_LEN * 8);
+
+    rope.insert(7, &paste);
+    assert!(rope.chunks().all(|c| c.len() <= MAX_LEAF_LEN));
+    assert_eq!(rope.len(), 13 + MAX_LEAF_LEN * 8);
+    assert_eq!(rope.find("World"), Some(7 + MAX_LEAF_LEN * 8));
+}
+
+#[test]
+fn test_rope_edits_collapse_empty_leaves() {
+    let mut rope = Rope::from_leaves(vec![Rope::new("Hello,"), Rope::new(" World!")]);
+
+    rope.insert(0, "Oh, ");
+    rope.insert(17, "!!");
+    assert!(rope.chunks().all(|c| !c.is_empty()));
+    assert_eq!(rope.chars().collect::<String>(), "Oh, Hello, World!!!");
+
+    rope.delete(0, 3);
+    assert!(rope.chunks().all(|c| !c.is_empty()));
+    assert_eq!(rope.chars().collect::<String>(), "Hello, World!!!");
+}
+
+#[test]
+fn test_rope_edits_merge_small_leaves() {
+    let mut rope = Rope::new("Hello, World!");
+    rope.insert(6, " Cruel");
+
+    assert!(rope.is_leaf());
+    assert_eq!(rope.chars().collect::<String>(), "Hello, Cruel World!");
+}
+
+#[test]
+#[should_panic(expected = "range starts at 4 but ends at 2")]
+fn test_delete_reversed() {
+    let mut rope = Rope::new("abcdef");
+    rope.delete(4, 2);
 }
@@ -0,0 +1,485 @@
+use std::ops::Range;
+
+use crate::rope::{Chars, Chunks, Rope};
+
+/// Something to search a rope for, like the patterns of `str::find`:
+/// a `&str`, a `char`, a `&[char]` matching any of its chars, a
+/// `FnMut(char) -> bool` predicate or, with the `regex` feature, a `&Regex`.
+pub trait RopePattern {
+    /// Byte range of the first match in `haystack`.
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>>;
+
+    /// Upper bound for the length of a match in bytes. The end of a chunk
+    /// is kept around this long to find matches spanning chunks. Patterns
+    /// without a bound return `usize::MAX`, and are run over the whole
+    /// searched text at once.
+    fn max_len(&self) -> usize;
+
+    /// Whether the pattern matches the empty string, as `""` does at every
+    /// offset. Empty matches are not iterated over, but `find` reports one.
+    fn matches_empty(&self) -> bool {
+        false
+    }
+}
+
+impl RopePattern for &str {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.find(*self).map(|i| i..i + self.len())
+    }
+
+    fn max_len(&self) -> usize {
+        self.len()
+    }
+
+    fn matches_empty(&self) -> bool {
+        str::is_empty(self)
+    }
+}
+
+impl RopePattern for char {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.find(*self).map(|i| i..i + self.len_utf8())
+    }
+
+    fn max_len(&self) -> usize {
+        self.len_utf8()
+    }
+}
+
+impl RopePattern for &[char] {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.find(*self).map(|i| {
+            let c = haystack[i..].chars().next().expect("match starts a char");
+            i..i + c.len_utf8()
+        })
+    }
+
+    fn max_len(&self) -> usize {
+        self.iter().map(|c| c.len_utf8()).max().unwrap_or(0)
+    }
+}
+
+impl<F: FnMut(char) -> bool> RopePattern for F {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack
+            .char_indices()
+            .find(|(_, c)| self(*c))
+            .map(|(i, c)| i..i + c.len_utf8())
+    }
+
+    fn max_len(&self) -> usize {
+        4
+    }
+}
+
+/// Matches of any length, so the searched text is gathered into one string
+/// before the regex runs over it. Empty matches are skipped.
+#[cfg(feature = "regex")]
+impl RopePattern for &regex::Regex {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        self.find_iter(haystack)
+            .find(|m| !m.is_empty())
+            .map(|m| m.range())
+    }
+
+    fn max_len(&self) -> usize {
+        usize::MAX
+    }
+}
+
+/// Location of a match, in both chars and bytes. Tools downstream disagree
+/// on units, so both are reported instead of converting per result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchRange {
+    pub char_range: Range<usize>,
+    pub byte_range: Range<usize>,
+}
+
+impl Rope {
+    /// Char offset of the first occurrence of `pat`, matching across chunk
+    /// boundaries.
+    pub fn find<P: RopePattern>(&self, pat: P) -> Option<usize> {
+        if pat.matches_empty() {
+            return Some(0);
+        }
+        self.match_indices(pat).next().map(|m| m.char_range.start)
+    }
+
+    /// Iterates over the non-overlapping occurrences of `pat`, left to right.
+    /// An empty pattern yields no matches.
+    pub fn match_indices<P: RopePattern>(&self, pat: P) -> MatchIndices<'_, P> {
+        self.match_indices_in(0..self.len_chars(), pat)
+    }
+
+    /// Char offset of the first occurrence of `pat` within the char range
+    /// `range`, clamped to the rope. Only the text in the range is scanned,
+    /// as when searching in a selection.
+    pub fn find_in<P: RopePattern>(&self, range: Range<usize>, pat: P) -> Option<usize> {
+        let end = range.end.min(self.len_chars());
+        let start = range.start.min(end);
+        if pat.matches_empty() {
+            return Some(start);
+        }
+        self.match_indices_in(start..end, pat)
+            .next()
+            .map(|m| m.char_range.start)
+    }
+
+    /// Like `match_indices`, but only scanning the char range `range`,
+    /// clamped to the rope. Matches lie entirely within the range.
+    pub fn match_indices_in<P: RopePattern>(
+        &self,
+        range: Range<usize>,
+        pat: P,
+    ) -> MatchIndices<'_, P> {
+        let end = range.end.min(self.len_chars());
+        let start = range.start.min(end);
+        let (start_byte, end_byte) = (self.char_to_byte(start), self.char_to_byte(end));
+        let (chunks, skip) = self.chunks_at_byte(start_byte);
+        MatchIndices {
+            chunks,
+            skip,
+            remaining: end_byte - start_byte,
+            pat,
+            // char and byte offsets coincide, skip counting chars
+            ascii: self.is_ascii(),
+            window: String::new(),
+            byte_offset: start_byte,
+            char_offset: start,
+            searched: 0,
+            counted: (0, 0),
+        }
+    }
+
+    /// Char offset of the last occurrence of `needle` ending at or before
+    /// char offset `offset`, for searching backwards from a cursor. Only the
+    /// text before `offset` is scanned, right to left.
+    pub fn rfind_from(&self, offset: usize, needle: &str) -> Option<usize> {
+        let offset = offset.min(self.len_chars());
+        if needle.is_empty() {
+            return Some(offset);
+        }
+        let mut end = self.char_to_byte(offset);
+        // start of the text right of the chunk, long enough to hold the rest
+        // of a match starting in it
+        let mut carry = String::new();
+        for chunk in self.chunks_rev_at(end) {
+            let window = chunk.to_string() + &carry;
+            end -= chunk.len();
+            if let Some(i) = window.rfind(needle) {
+                return Some(self.byte_to_char(end + i));
+            }
+            let mut keep = (needle.len() - 1).min(window.len());
+            while !window.is_char_boundary(keep) {
+                keep -= 1;
+            }
+            carry = window[..keep].to_string();
+        }
+        None
+    }
+
+    /// Iterates over the parts of the rope separated by `pat`, as ropes
+    /// sharing their leaves with self. Like `str::split`, `n` matches make
+    /// `n + 1` parts.
+    pub fn split_on<P: RopePattern>(&self, pat: P) -> SplitOn<'_, P> {
+        SplitOn {
+            rope: self,
+            matches: self.match_indices(pat),
+            start: Some(0),
+        }
+    }
+
+    /// Iterates over the chars of the rope along with their char and byte
+    /// offsets.
+    pub fn char_indices(&self) -> CharIndices<'_> {
+        CharIndices {
+            chars: self.chars(),
+            char_idx: 0,
+            byte_idx: 0,
+        }
+    }
+}
+
+pub struct MatchIndices<'a, P> {
+    chunks: Chunks<'a>,
+    // bytes of the first chunk before the start of the search
+    skip: usize,
+    // bytes of the searched range not in the window yet
+    remaining: usize,
+    pat: P,
+    ascii: bool,
+    // text of the chunks not fully searched yet
+    window: String,
+    // offsets of the start of the window in the rope
+    byte_offset: usize,
+    char_offset: usize,
+    // bytes of the window already searched
+    searched: usize,
+    // (bytes, chars) of the window whose chars have been counted
+    counted: (usize, usize),
+}
+
+impl<'a, P> MatchIndices<'a, P> {
+    // char offset within the window of byte offset `byte`, counting forward
+    // from the last counted position
+    fn chars_up_to(&mut self, byte: usize) -> usize {
+        if self.ascii {
+            return byte;
+        }
+        let (b, c) = self.counted;
+        self.counted = (byte, c + self.window[b..byte].chars().count());
+        self.counted.1
+    }
+}
+
+impl<'a, P: RopePattern> Iterator for MatchIndices<'a, P> {
+    type Item = MatchRange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pat.max_len() == 0 {
+            return None;
+        }
+        // an unbounded pattern could match further than the window goes
+        let whole = self.pat.max_len() == usize::MAX;
+        loop {
+            let found = match whole && self.remaining > 0 {
+                true => None,
+                false => self.pat.find_in(&self.window[self.searched..]),
+            };
+            if let Some(found) = found {
+                let start = self.searched + found.start;
+                let end = self.searched + found.end;
+                let char_start = self.char_offset + self.chars_up_to(start);
+                let char_end = self.char_offset + self.chars_up_to(end);
+                self.searched = end;
+                return Some(MatchRange {
+                    char_range: char_start..char_end,
+                    byte_range: self.byte_offset + start..self.byte_offset + end,
+                });
+            }
+
+            // keep the tail that could still start a match spanning chunks
+            let mut keep = self
+                .window
+                .len()
+                .saturating_sub(self.pat.max_len() - 1)
+                .max(self.searched);
+            while !self.window.is_char_boundary(keep) {
+                keep += 1;
+            }
+            self.char_offset += self.chars_up_to(keep);
+            self.byte_offset += keep;
+            self.window.drain(..keep);
+            self.searched = 0;
+            self.counted = (0, 0);
+
+            if self.remaining == 0 {
+                return None;
+            }
+            let chunk = &self.chunks.next()?[std::mem::take(&mut self.skip)..];
+            let chunk = &chunk[..chunk.len().min(self.remaining)];
+            self.remaining -= chunk.len();
+            self.window.push_str(chunk);
+        }
+    }
+}
+
+pub struct SplitOn<'a, P> {
+    rope: &'a Rope,
+    matches: MatchIndices<'a, P>,
+    // char offset of the next part, none once the last one is out
+    start: Option<usize>,
+}
+
+impl<'a, P: RopePattern> Iterator for SplitOn<'a, P> {
+    type Item = Rope;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.start?;
+        match self.matches.next() {
+            Some(m) => {
+                self.start = Some(m.char_range.end);
+                Some(self.rope.slice(start..m.char_range.start).to_rope())
+            }
+            None => {
+                self.start = None;
+                Some(self.rope.slice(start..self.rope.len_chars()).to_rope())
+            }
+        }
+    }
+}
+
+pub struct CharIndices<'a> {
+    chars: Chars<'a>,
+    char_idx: usize,
+    byte_idx: usize,
+}
+
+impl<'a> Iterator for CharIndices<'a> {
+    /// (char offset, byte offset, char)
+    type Item = (usize, usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let c = self.chars.next()?;
+        let item = (self.char_idx, self.byte_idx, c);
+        self.char_idx += 1;
+        self.byte_idx += c.len_utf8();
+        Some(item)
+    }
+}
+
+#[test]
+fn test_find() {
+    let rope = Rope::new("Hello, World!");
+    assert_eq!(rope.find("World"), Some(7));
+    assert_eq!(rope.find("world"), None);
+    assert_eq!(rope.find(""), Some(0));
+    assert_eq!(rope.find(&[][..]), None);
+    assert_eq!(rope.find_in(2..5, &[][..]), None);
+    assert_eq!(rope.find(&['W', 'o'][..]), Some(4));
+}
+
+#[test]
+fn test_find_across_chunks() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("Hello, Wo"),
+        Rope::new("rld! Grüße, Wor"),
+        Rope::new("ld"),
+    ]);
+
+    assert_eq!(rope.find("World"), Some(7));
+    assert_eq!(rope.find("ße, World"), Some(17));
+}
+
+#[test]
+fn test_rfind_from() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("one tw"),
+        Rope::new("o thrée "),
+        Rope::new("two"),
+    ]);
+
+    assert_eq!(rope.rfind_from(rope.len_chars(), "two"), Some(14));
+    assert_eq!(rope.rfind_from(16, "two"), Some(4));
+    assert_eq!(rope.rfind_from(7, "two"), Some(4));
+    assert_eq!(rope.rfind_from(6, "two"), None);
+    assert_eq!(rope.rfind_from(13, "ée"), Some(11));
+    assert_eq!(rope.rfind_from(12, "ée"), None);
+    assert_eq!(rope.rfind_from(5, ""), Some(5));
+}
+
+#[test]
+fn test_match_indices() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("Grüße, Wo"),
+        Rope::new("rld! Grüße, Wor"),
+        Rope::new("ld"),
+    ]);
+
+    let matches: Vec<MatchRange> = rope.match_indices("World").collect();
+    assert_eq!(
+        matches,
+        vec![
+            MatchRange {
+                char_range: 7..12,
+                byte_range: 9..14,
+            },
+            MatchRange {
+                char_range: 21..26,
+                byte_range: 25..30,
+            },
+        ]
+    );
+    assert_eq!(rope.match_indices("aa").count(), 0);
+    assert_eq!(Rope::new("aaaa").match_indices("aa").count(), 2);
+}
+
+#[test]
+fn test_search_in_range() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("Grüße, Wo"),
+        Rope::new("rld! Grüße, Wor"),
+        Rope::new("ld"),
+    ]);
+
+    let matches: Vec<MatchRange> = rope.match_indices_in(8..26, "World").collect();
+    assert_eq!(
+        matches,
+        vec![MatchRange {
+            char_range: 21..26,
+            byte_range: 25..30,
+        }]
+    );
+    // a match running past the end of the range is not one
+    let matches: Vec<MatchRange> = rope.match_indices_in(7..25, "World").collect();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].char_range, 7..12);
+
+    assert_eq!(rope.find_in(3..20, 'r'), Some(9));
+    assert_eq!(rope.find_in(10..100, "Grüße"), Some(14));
+    assert_eq!(rope.find_in(15..20, "Grüße"), None);
+    assert_eq!(rope.find_in(4..4, ""), Some(4));
+    assert_eq!(rope.find_in(14..14, "G"), None);
+}
+
+#[test]
+fn test_patterns() {
+    let rope = Rope::from_leaves(vec![Rope::new("let x = 1;"), Rope::new(" // é\tend")]);
+
+    assert_eq!(rope.find('='), Some(6));
+    assert_eq!(rope.find('é'), Some(14));
+    assert_eq!(rope.find(&['/', ';'][..]), Some(9));
+    assert_eq!(rope.find(char::is_numeric), Some(8));
+    assert_eq!(rope.find(|c: char| c == '\t'), Some(15));
+    assert_eq!(rope.match_indices(char::is_whitespace).count(), 6);
+
+    let matches: Vec<MatchRange> = rope.match_indices(&['é', 'x'][..]).collect();
+    assert_eq!(matches[1].char_range, 14..15);
+    assert_eq!(matches[1].byte_range, 14..16);
+}
+
+#[test]
+fn test_split_on() {
+    let rope = Rope::from_leaves(vec![Rope::new("a, b,"), Rope::new(" c, ")]);
+    let parts: Vec<String> = rope
+        .split_on(", ")
+        .map(|part| part.chars().collect())
+        .collect();
+    assert_eq!(parts, vec!["a", "b", "c", ""]);
+
+    let parts: Vec<String> = rope
+        .split_on(char::is_alphabetic)
+        .map(|part| part.chars().collect())
+        .collect();
+    assert_eq!(parts, vec!["", ", ", ", ", ", "]);
+    assert_eq!(Rope::new("abc").split_on('x').count(), 1);
+}
+
+#[test]
+fn test_char_indices() {
+    let rope = Rope::from_leaves(vec![Rope::new("aé"), Rope::new("b")]);
+
+    let indices: Vec<(usize, usize, char)> = rope.char_indices().collect();
+    assert_eq!(indices, vec![(0, 0, 'a'), (1, 1, 'é'), (2, 3, 'b')]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_find_regex() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("id: 12"),
+        Rope::new("34, grüße: 5"),
+        Rope::new("6"),
+    ]);
+    let digits = regex::Regex::new(r"[0-9]+").unwrap();
+
+    assert_eq!(rope.find(&digits), Some(4));
+    let found: Vec<_> = rope.match_indices(&digits).map(|m| m.char_range).collect();
+    assert_eq!(found, vec![4..8, 17..19]);
+    let parts: Vec<String> = rope.split_on(&digits).map(|p| p.to_string()).collect();
+    assert_eq!(parts, vec!["id: ", ", grüße: ", ""]);
+    assert_eq!(rope.find_in(9..17, &digits), None);
+
+    let nothing = regex::Regex::new(r"x*").unwrap();
+    assert_eq!(rope.find(&nothing), None);
+}
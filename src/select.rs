@@ -0,0 +1,189 @@
+use std::ops::Range;
+
+use crate::rope::Rope;
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Text units a selection can be expanded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Word,
+    /// Whole lines, line break included.
+    Line,
+    /// A run of non-blank lines.
+    Paragraph,
+    /// The text between the innermost enclosing pair of brackets, or the
+    /// pair itself when the selection already covers that text.
+    Bracket,
+}
+
+impl Rope {
+    /// The range of the `unit` enclosing the char range `range`, for "expand
+    /// selection" commands. Returns `range` unchanged when it is not inside
+    /// such a unit.
+    pub fn expand_selection(&self, range: Range<usize>, unit: Unit) -> Range<usize> {
+        let len = self.len_chars();
+        let range = range.start.min(len)..range.end.min(len).max(range.start.min(len));
+        match unit {
+            Unit::Word => self.expand_word(range),
+            Unit::Line => {
+                let (first, last) = self.line_span(&range);
+                self.line_to_char(first)..self.line_to_char(last + 1)
+            }
+            Unit::Paragraph => self.expand_paragraph(range),
+            Unit::Bracket => self.expand_bracket(range.clone()).unwrap_or(range),
+        }
+    }
+
+    fn expand_word(&self, range: Range<usize>) -> Range<usize> {
+        let len = self.len_chars();
+        let is_word = |i: usize| {
+            self.chars_at(i)
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        };
+        let (mut start, mut end) = (range.start, range.end);
+        while start > 0 && is_word(start - 1) {
+            start -= 1;
+        }
+        while end < len && is_word(end) {
+            end += 1;
+        }
+        start..end
+    }
+
+    fn expand_paragraph(&self, range: Range<usize>) -> Range<usize> {
+        let is_blank = |line: usize| self.line_chars(line).all(char::is_whitespace);
+        let (mut first, mut last) = self.line_span(&range);
+        while first > 0 && !is_blank(first - 1) {
+            first -= 1;
+        }
+        while last + 1 < self.len_lines() && !is_blank(last + 1) {
+            last += 1;
+        }
+        self.line_to_char(first)..self.line_to_char(last + 1)
+    }
+
+    // one pass right to left from the range for the open brackets, and at
+    // most one pass left to right for the closing ones, whatever the number
+    // of brackets left unmatched
+    fn expand_bracket(&self, range: Range<usize>) -> Option<Range<usize>> {
+        // after the range start, the closing brackets of each kind with no
+        // open one of their kind before them, in order: the n-th one closes
+        // the n-th unmatched open bracket of its kind before the range start
+        let mut closes: [Vec<usize>; BRACKETS.len()] = Default::default();
+        let mut ahead = [0usize; BRACKETS.len()];
+        let mut after = self.chars_at(range.start).enumerate().fuse();
+
+        let mut behind = [0usize; BRACKETS.len()];
+        let mut opened = [0usize; BRACKETS.len()];
+        let before = self
+            .chunks_rev_at(self.char_to_byte(range.start))
+            .flat_map(|chunk| chunk.chars().rev());
+        for (i, c) in before.enumerate() {
+            let kind = match bracket_kind(c) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            if c == BRACKETS[kind].1 {
+                behind[kind] += 1;
+                continue;
+            }
+            if behind[kind] > 0 {
+                behind[kind] -= 1;
+                continue;
+            }
+            let n = opened[kind];
+            opened[kind] += 1;
+
+            while closes[kind].len() <= n {
+                let (j, c) = match after.next() {
+                    Some(next) => next,
+                    None => break,
+                };
+                if let Some(other) = bracket_kind(c) {
+                    if c == BRACKETS[other].0 {
+                        ahead[other] += 1;
+                    } else if ahead[other] > 0 {
+                        ahead[other] -= 1;
+                    } else {
+                        closes[other].push(range.start + j);
+                    }
+                }
+            }
+            // pairs closing inside the range don't enclose it, look further out
+            match closes[kind].get(n) {
+                Some(&close_at) if close_at >= range.end => {
+                    let open_at = range.start - 1 - i;
+                    let inner = open_at + 1..close_at;
+                    if inner == range {
+                        return Some(open_at..close_at + 1);
+                    }
+                    return Some(inner);
+                }
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    // first and last line touched by `range`
+    fn line_span(&self, range: &Range<usize>) -> (usize, usize) {
+        let first = self.char_to_line(range.start);
+        let last = self.char_to_line(range.end.max(range.start + 1) - 1);
+        (first, last.max(first))
+    }
+}
+
+// index in BRACKETS of the pair `c` belongs to
+fn bracket_kind(c: char) -> Option<usize> {
+    BRACKETS
+        .iter()
+        .position(|&(open, close)| c == open || c == close)
+}
+
+#[test]
+fn test_expand_word_and_line() {
+    let rope = Rope::new("let answer = 42;\nprint(answer)\n");
+
+    assert_eq!(rope.expand_selection(6..6, Unit::Word), 4..10);
+    assert_eq!(rope.expand_selection(4..10, Unit::Word), 4..10);
+    assert_eq!(rope.expand_selection(11..11, Unit::Word), 11..11);
+
+    assert_eq!(rope.expand_selection(6..8, Unit::Line), 0..17);
+    assert_eq!(rope.expand_selection(6..20, Unit::Line), 0..31);
+    assert_eq!(rope.expand_selection(0..17, Unit::Line), 0..17);
+}
+
+#[test]
+fn test_expand_paragraph() {
+    let rope = Rope::new("one\ntwo\n\nthree\nfour\n\n");
+
+    assert_eq!(rope.expand_selection(5..5, Unit::Paragraph), 0..8);
+    assert_eq!(rope.expand_selection(12..16, Unit::Paragraph), 9..20);
+}
+
+#[test]
+fn test_expand_bracket() {
+    let rope = Rope::new("f(a, [b, c], {d: (e)})");
+
+    // inside [b, c]
+    assert_eq!(rope.expand_selection(7..7, Unit::Bracket), 6..10);
+    assert_eq!(rope.expand_selection(6..10, Unit::Bracket), 5..11);
+    assert_eq!(rope.expand_selection(5..11, Unit::Bracket), 2..21);
+    assert_eq!(rope.expand_selection(2..21, Unit::Bracket), 1..22);
+    assert_eq!(rope.expand_selection(1..22, Unit::Bracket), 1..22);
+
+    // the selection crosses the closing bracket of (e)
+    assert_eq!(rope.expand_selection(18..20, Unit::Bracket), 14..20);
+
+    // brackets left open or closed inside the selection are skipped
+    let rope = Rope::new("{ ((( [x) ] }");
+    assert_eq!(rope.expand_selection(7..7, Unit::Bracket), 7..10);
+    assert_eq!(rope.expand_selection(6..9, Unit::Bracket), 1..12);
+    let rope = Rope::new(&"(".repeat(10_000));
+    assert_eq!(
+        rope.expand_selection(5_000..5_000, Unit::Bracket),
+        5_000..5_000
+    );
+}
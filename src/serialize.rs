@@ -0,0 +1,87 @@
+use std::fmt;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use crate::builder::RopeBuilder;
+use crate::rope::Rope;
+
+/// Serializes the text as a sequence of chunks, the leaves as they are, so
+/// nothing is copied into one string first. The shape of the tree is not
+/// kept; `write_structure` does that.
+impl Serialize for Rope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.chunks().count()))?;
+        for chunk in self.chunks() {
+            seq.serialize_element(chunk)?;
+        }
+        seq.end()
+    }
+}
+
+/// Deserializes a sequence of chunks into a balanced rope with full leaves,
+/// whatever the size of the chunks.
+impl<'de> Deserialize<'de> for Rope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Rope, D::Error> {
+        deserializer.deserialize_seq(RopeVisitor)
+    }
+}
+
+struct RopeVisitor;
+
+impl<'de> Visitor<'de> for RopeVisitor {
+    type Value = Rope;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of strings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Rope, A::Error> {
+        let mut builder = RopeBuilder::new();
+        while seq.next_element_seed(Chunk(&mut builder))?.is_some() {}
+        Ok(builder.finish())
+    }
+}
+
+// appends a chunk straight to the builder, without an owned copy of it
+struct Chunk<'a>(&'a mut RopeBuilder);
+
+impl<'de> DeserializeSeed<'de> for Chunk<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Chunk<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<(), E> {
+        self.0.append(s);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_serde_roundtrip() {
+    let text = "grüße\n".repeat(1000);
+    let rope = Rope::from_leaves(vec![Rope::new("a"), Rope::new("b"), Rope::chunked(&text)]);
+
+    let json = serde_json::to_string(&rope).unwrap();
+    assert!(json.starts_with(r#"["a","b","#));
+    let back: Rope = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, rope);
+    // small chunks are packed back together
+    assert!(back.chunks().count() < rope.chunks().count());
+
+    let back: Rope = serde_json::from_str(r#"["one\n", "", "two"]"#).unwrap();
+    assert_eq!(back, "one\ntwo");
+    let empty: Rope = serde_json::from_str("[]").unwrap();
+    assert!(empty.is_empty());
+    assert!(serde_json::from_str::<Rope>("[1]").is_err());
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use crate::history::History;
+use crate::rope::Rope;
+use crate::structure::{self, invalid, read_usize, write_usize};
+
+const MAGIC: &[u8; 4] = b"RPX1";
+
+/// Everything needed to restore an editing session: the text with its undo
+/// history, named marks and the selections, all as char offsets into the
+/// current text.
+#[derive(Debug)]
+pub struct Session {
+    pub history: History,
+    pub marks: BTreeMap<String, usize>,
+    pub selections: Vec<Range<usize>>,
+}
+
+impl Session {
+    pub fn new(rope: Rope) -> Session {
+        Session {
+            history: History::new(rope),
+            marks: BTreeMap::new(),
+            selections: Vec::new(),
+        }
+    }
+
+    /// The current text.
+    pub fn content(&self) -> &Rope {
+        self.history.current()
+    }
+
+    /// Writes the whole session. All versions of the text are stored in one
+    /// structure, so what they share is only written once.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let history = &self.history;
+        let versions: Vec<&Rope> = history
+            .undo
+            .iter()
+            .chain(Some(&history.current))
+            .chain(&history.redo)
+            .collect();
+
+        writer.write_all(MAGIC)?;
+        let roots = structure::write_trees(&mut writer, &versions)?;
+        write_usize(&mut writer, history.undo.len())?;
+        write_usize(&mut writer, history.redo.len())?;
+        for root in roots {
+            write_usize(&mut writer, root)?;
+        }
+        match history.budget {
+            Some(budget) => {
+                writer.write_all(&[1])?;
+                write_usize(&mut writer, budget)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+
+        write_usize(&mut writer, self.marks.len())?;
+        for (name, &offset) in &self.marks {
+            write_usize(&mut writer, name.len())?;
+            writer.write_all(name.as_bytes())?;
+            write_usize(&mut writer, offset)?;
+        }
+        write_usize(&mut writer, self.selections.len())?;
+        for selection in &self.selections {
+            write_usize(&mut writer, selection.start)?;
+            write_usize(&mut writer, selection.end)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a session written by `save`. Malformed input, including marks
+    /// or selections outside the text, is reported as an `InvalidData` error.
+    pub fn load<R: Read>(mut reader: R) -> io::Result<Session> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a session"));
+        }
+
+        let trees = structure::read_trees(&mut reader)?;
+        let undo_len = read_usize(&mut reader)?;
+        let redo_len = read_usize(&mut reader)?;
+        let count = undo_len
+            .checked_add(redo_len)
+            .and_then(|n| n.checked_add(1))
+            .ok_or_else(|| invalid("too many versions"))?;
+        let mut versions = VecDeque::new();
+        for _ in 0..count {
+            let tree = trees
+                .get(read_usize(&mut reader)?)
+                .ok_or_else(|| invalid("unknown version"))?;
+            versions.push_back(tree.share());
+        }
+        let mut flag = [0];
+        reader.read_exact(&mut flag)?;
+        let budget = match flag[0] {
+            0 => None,
+            _ => Some(read_usize(&mut reader)?),
+        };
+
+        let redo = versions.split_off(undo_len + 1).into();
+        let current = versions.pop_back().expect("one current version");
+        let len = current.len_chars();
+        let history = History::from_parts(versions, current, redo, budget);
+
+        let mut marks = BTreeMap::new();
+        for _ in 0..read_usize(&mut reader)? {
+            let name = read_string(&mut reader)?;
+            let offset = read_usize(&mut reader)?;
+            if offset > len {
+                return Err(invalid("mark out of the text"));
+            }
+            marks.insert(name, offset);
+        }
+        let mut selections = Vec::new();
+        for _ in 0..read_usize(&mut reader)? {
+            let start = read_usize(&mut reader)?;
+            let end = read_usize(&mut reader)?;
+            if start > end || end > len {
+                return Err(invalid("selection out of the text"));
+            }
+            selections.push(start..end);
+        }
+
+        Ok(Session {
+            history,
+            marks,
+            selections,
+        })
+    }
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = read_usize(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|_| invalid("name is not UTF-8"))
+}
+
+#[test]
+fn test_session_roundtrip() {
+    let mut session = Session::new(Rope::new("fn main() {}\n"));
+    session
+        .history
+        .edit(|rope| rope.insert(11, "\n    run();\n"));
+    session.history.edit(|rope| rope.push_str("// end\n"));
+    session.history.edit(|rope| rope.push_str("// more\n"));
+    session.history.undo();
+    session.marks.insert("a".to_string(), 16);
+    session.selections = vec![0..2, 16..22];
+
+    let mut saved = Vec::new();
+    session.save(&mut saved).unwrap();
+    let mut restored = Session::load(saved.as_slice()).unwrap();
+
+    assert_eq!(
+        restored.content().to_string(),
+        session.content().to_string()
+    );
+    assert_eq!(restored.marks, session.marks);
+    assert_eq!(restored.selections, session.selections);
+    assert_eq!(restored.history.undo_len(), 2);
+    assert_eq!(restored.history.redo_len(), 1);
+
+    assert!(restored.history.redo());
+    assert!(restored.content().to_string().ends_with("// more\n"));
+    while restored.history.undo() {}
+    assert_eq!(restored.content().to_string(), "fn main() {}\n");
+}
+
+#[test]
+fn test_session_rejects_bad_input() {
+    let mut session = Session::new(Rope::new("short"));
+    session.marks.insert("far".to_string(), 50);
+    let mut saved = Vec::new();
+    session.save(&mut saved).unwrap();
+
+    let err = Session::load(saved.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut session = Session::new(Rope::new("short"));
+    session.selections = vec![0..5, 3..9];
+    let mut saved = Vec::new();
+    session.save(&mut saved).unwrap();
+    let err = Session::load(saved.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(Session::load(&b"RPS1"[..]).is_err());
+}
@@ -0,0 +1,193 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::rope::{Leaf, Rope, MAX_LEAF_LEN};
+
+// about one line in this many ends a chunk, on top of the size limit
+const CUT_ONE_IN: u64 = 8;
+
+/// Loads documents with their chunks deduplicated: identical chunks across
+/// all the documents loaded through the same set share one buffer.
+///
+/// Chunk boundaries are picked from the content of the lines rather than
+/// their position, so common text such as license headers or generated
+/// code lines up into the same chunks from one file to the next.
+#[derive(Debug, Default)]
+pub struct RopeSet {
+    chunks: HashSet<Chunk>,
+}
+
+// a chunk looked up by its text, so lookups don't copy it
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Chunk(Arc<String>);
+
+impl Borrow<str> for Chunk {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl RopeSet {
+    pub fn new() -> RopeSet {
+        RopeSet::default()
+    }
+
+    /// Number of distinct chunks held by the set.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Drops the chunks no rope uses any more, which the set would otherwise
+    /// hold on to for as long as it lives.
+    pub fn release_unused(&mut self) {
+        self.chunks.retain(|chunk| Arc::strong_count(&chunk.0) > 1);
+    }
+
+    /// Builds a rope over `text`, reusing the chunks already in the set.
+    pub fn intern(&mut self, text: &str) -> Rope {
+        let mut leaves = Vec::new();
+        let mut start = 0;
+        for end in cut_points(text) {
+            let buf = self.chunk(&text[start..end]);
+            leaves.push(Rope::Leaf(Leaf::view(buf, 0, end - start)));
+            start = end;
+        }
+        Rope::from_leaves(leaves)
+    }
+
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Rope> {
+        Ok(self.intern(&fs::read_to_string(path)?))
+    }
+
+    /// Loads every file in `paths`, in order.
+    pub fn load_many<I, P>(&mut self, paths: I) -> io::Result<Vec<Rope>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        paths.into_iter().map(|path| self.load(path)).collect()
+    }
+
+    fn chunk(&mut self, s: &str) -> Arc<String> {
+        if let Some(chunk) = self.chunks.get(s) {
+            return Arc::clone(&chunk.0);
+        }
+        let buf = Arc::new(s.to_string());
+        self.chunks.insert(Chunk(Arc::clone(&buf)));
+        buf
+    }
+}
+
+// byte offsets at which the chunks of `text` end
+fn cut_points(text: &str) -> Vec<usize> {
+    let mut cuts = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    while pos < text.len() {
+        let line_end = text[pos..].find('\n').map_or(text.len(), |i| pos + i + 1);
+        if line_end - start > MAX_LEAF_LEN {
+            if pos > start {
+                cuts.push(pos);
+                start = pos;
+                continue;
+            }
+            // a single line longer than a leaf, cut at a char boundary
+            let mut cut = start + MAX_LEAF_LEN;
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            cuts.push(cut);
+            start = cut;
+            pos = cut;
+            continue;
+        }
+        let mut hasher = DefaultHasher::new();
+        text[pos..line_end].hash(&mut hasher);
+        pos = line_end;
+        if hasher.finish().is_multiple_of(CUT_ONE_IN) {
+            cuts.push(pos);
+            start = pos;
+        }
+    }
+    if start < text.len() {
+        cuts.push(text.len());
+    }
+    cuts
+}
+
+#[test]
+fn test_rope_set_shares_common_chunks() {
+    let header: String = (0..200)
+        .map(|i| format!("// license line {}\n", i))
+        .collect();
+    let first = format!("{}fn first() {{}}\n", header);
+    let second = format!("{}fn second() {{}}\n", header);
+
+    let mut set = RopeSet::new();
+    let a = set.intern(&first);
+    let after_first = set.chunk_count();
+    let b = set.intern(&second);
+
+    assert_eq!(a.chars().collect::<String>(), first);
+    assert_eq!(b.chars().collect::<String>(), second);
+    assert!(a.chunks().all(|chunk| chunk.len() <= MAX_LEAF_LEN));
+    // only the chunk holding the last line differs
+    assert!(set.chunk_count() - after_first <= 2);
+}
+
+#[test]
+fn test_rope_set_long_lines() {
+    let text = "é".repeat(MAX_LEAF_LEN) + "\nend";
+    let mut set = RopeSet::new();
+    let rope = set.intern(&text);
+
+    assert_eq!(rope.chars().collect::<String>(), text);
+    assert!(rope.chunks().all(|chunk| chunk.len() <= MAX_LEAF_LEN));
+    assert_eq!(set.intern("").len(), 0);
+}
+
+#[test]
+fn test_rope_set_load_many() {
+    let dir = std::env::temp_dir().join(format!("rope-set-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let paths = vec![dir.join("a.txt"), dir.join("b.txt")];
+    fs::write(&paths[0], "same\ntext\n").unwrap();
+    fs::write(&paths[1], "same\ntext\n").unwrap();
+
+    let mut set = RopeSet::new();
+    let ropes = set.load_many(&paths).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(ropes.len(), 2);
+    assert_eq!(ropes[1].chars().collect::<String>(), "same\ntext\n");
+    let ptrs = |rope: &Rope| rope.chunks().map(str::as_ptr).collect::<Vec<_>>();
+    assert_eq!(ptrs(&ropes[0]), ptrs(&ropes[1]));
+}
+
+#[test]
+fn test_rope_set_release_unused() {
+    let mut set = RopeSet::new();
+    let kept = set.intern("kept\n");
+    let dropped = set.intern("dropped\n");
+    let copy = dropped.share();
+    assert_eq!(set.chunk_count(), 2);
+
+    drop(dropped);
+    set.release_unused();
+    assert_eq!(set.chunk_count(), 2);
+
+    drop(copy);
+    set.release_unused();
+    assert_eq!(set.chunk_count(), 1);
+    let again = set.intern("kept\n");
+    assert_eq!(
+        again.chunks().next().map(str::as_ptr),
+        kept.chunks().next().map(str::as_ptr)
+    );
+}
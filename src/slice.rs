@@ -0,0 +1,307 @@
+use std::fmt;
+use std::iter::Take;
+use std::ops::{Bound, Range, RangeBounds};
+
+use crate::rope::{Chars, Chunks, Rope};
+
+/// A range of chars of a rope, borrowing its leaves instead of copying them.
+#[derive(Clone, Copy)]
+pub struct RopeSlice<'a> {
+    rope: &'a Rope,
+    start_char: usize,
+    end_char: usize,
+    start_byte: usize,
+    end_byte: usize,
+}
+
+impl Rope {
+    /// The chars in `range`, clamped to the rope, as a slice borrowing the
+    /// rope. Nothing is copied until the slice is turned into a rope or a
+    /// string.
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'_> {
+        let end_char = range.end.min(self.len_chars());
+        let start_char = range.start.min(end_char);
+        RopeSlice {
+            rope: self,
+            start_char,
+            end_char,
+            start_byte: self.char_to_byte(start_char),
+            end_byte: self.char_to_byte(end_char),
+        }
+    }
+
+    /// Copies the chars in `range` out into a string. The range takes any
+    /// form, `..` included, and is clamped to the rope like in `slice`.
+    pub fn slice_to_string<R: RangeBounds<usize>>(&self, range: R) -> String {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.saturating_add(1),
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len_chars(),
+        };
+        self.slice(start..end).to_string()
+    }
+
+    /// The bytes in `range` as a slice, for callers working in byte offsets
+    /// such as parsers or language servers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or either end falls inside a
+    /// char.
+    pub fn byte_slice<R: RangeBounds<usize>>(&self, range: R) -> RopeSlice<'_> {
+        let range = self.byte_range(range);
+        RopeSlice {
+            rope: self,
+            start_char: self.byte_to_char(range.start),
+            end_char: self.byte_to_char(range.end),
+            start_byte: range.start,
+            end_byte: range.end,
+        }
+    }
+}
+
+impl<'a> RopeSlice<'a> {
+    pub fn len_chars(&self) -> usize {
+        self.end_char - self.start_char
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        self.end_byte - self.start_byte
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start_char == self.end_char
+    }
+
+    /// The chars in `range` of this slice, clamped to it.
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'a> {
+        let start = self.start_char + range.start.min(self.len_chars());
+        let end = self.start_char + range.end.min(self.len_chars());
+        self.rope.slice(start..end.max(start))
+    }
+
+    pub fn chars(&self) -> Take<Chars<'a>> {
+        self.rope.chars_at(self.start_char).take(self.len_chars())
+    }
+
+    /// Iterates over the parts of the leaves covered by the slice.
+    pub fn chunks(&self) -> SliceChunks<'a> {
+        let (chunks, skip) = self.rope.chunks_at_byte(self.start_byte);
+        SliceChunks {
+            chunks,
+            skip,
+            left: self.len_bytes(),
+        }
+    }
+
+    /// Copies the slice out into a rope of its own. Subtrees lying inside the
+    /// slice are shared, only the boundary leaves are copied.
+    pub fn to_rope(&self) -> Rope {
+        let (_, rest) = self.rope.split(self.start_byte);
+        rest.split(self.len_bytes()).0
+    }
+}
+
+pub struct SliceChunks<'a> {
+    chunks: Chunks<'a>,
+    // bytes to skip at the start of the next chunk
+    skip: usize,
+    // bytes of the slice not yielded yet
+    left: usize,
+}
+
+impl<'a> Iterator for SliceChunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.left > 0 {
+            let chunk = &self.chunks.next()?[self.skip..];
+            self.skip = 0;
+            let chunk = &chunk[..chunk.len().min(self.left)];
+            self.left -= chunk.len();
+            if !chunk.is_empty() {
+                return Some(chunk);
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for RopeSlice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+impl fmt::Debug for RopeSlice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+impl PartialEq for RopeSlice<'_> {
+    fn eq(&self, other: &RopeSlice<'_>) -> bool {
+        self.len_bytes() == other.len_bytes()
+            && self
+                .chunks()
+                .flat_map(str::bytes)
+                .eq(other.chunks().flat_map(str::bytes))
+    }
+}
+
+impl Eq for RopeSlice<'_> {}
+
+impl PartialEq<str> for RopeSlice<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.len_bytes() == other.len() && self.chunks().flat_map(str::bytes).eq(other.bytes())
+    }
+}
+
+impl PartialEq<&str> for RopeSlice<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for RopeSlice<'_> {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Rope> for RopeSlice<'_> {
+    fn eq(&self, other: &Rope) -> bool {
+        self.len_bytes() == other.len()
+            && self
+                .chunks()
+                .flat_map(str::bytes)
+                .eq(other.chunks().flat_map(str::bytes))
+    }
+}
+
+impl PartialEq<RopeSlice<'_>> for Rope {
+    fn eq(&self, other: &RopeSlice<'_>) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<RopeSlice<'_>> for str {
+    fn eq(&self, other: &RopeSlice<'_>) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<RopeSlice<'_>> for &str {
+    fn eq(&self, other: &RopeSlice<'_>) -> bool {
+        other == *self
+    }
+}
+
+impl PartialEq<RopeSlice<'_>> for String {
+    fn eq(&self, other: &RopeSlice<'_>) -> bool {
+        other == self.as_str()
+    }
+}
+
+#[test]
+fn test_slice_borrows() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("Grüße"),
+        Rope::new(", "),
+        Rope::new("World!"),
+    ]);
+
+    let slice = rope.slice(3..9);
+    assert_eq!(slice, "ße, Wo");
+    assert_eq!(slice.len_chars(), 6);
+    assert_eq!(slice.len_bytes(), 7);
+    assert_eq!(slice.chunks().collect::<Vec<_>>(), vec!["ße", ", ", "Wo"]);
+    assert_eq!(slice.chars().last(), Some('o'));
+    assert_eq!(slice.to_string(), "ße, Wo");
+
+    let inner = slice.slice(1..4);
+    assert_eq!(inner, "e, ");
+    assert_eq!(inner, rope.slice(4..7));
+    assert_ne!(inner, rope.slice(4..8));
+    assert!(slice.slice(6..10).is_empty());
+
+    // chunks point into the leaves, nothing was copied
+    let leaf = rope.chunks().next().unwrap();
+    let first = slice.chunks().next().unwrap();
+    assert_eq!(first.as_ptr(), leaf[4..].as_ptr());
+}
+
+#[test]
+fn test_slice_eq() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße"), Rope::new(", World!")]);
+    let slice = rope.slice(7..12);
+
+    assert_eq!(slice, "World");
+    assert_eq!(slice, String::from("World"));
+    assert!("World" == slice);
+    let owned = String::from("World");
+    assert!(owned == slice);
+    assert_ne!(slice, "Worl");
+
+    // a rope equals a slice with the same text, however it is cut
+    let world = Rope::new("World");
+    assert_eq!(slice, world);
+    assert_eq!(world, slice);
+    assert_eq!(rope, rope.slice(0..rope.len_chars()));
+    assert_ne!(rope, slice);
+    assert_eq!(Rope::empty(), rope.slice(3..3));
+}
+
+#[test]
+fn test_slice_to_string() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("Grüße"),
+        Rope::new(", "),
+        Rope::new("World!"),
+    ]);
+
+    assert_eq!(rope.slice_to_string(..), "Grüße, World!");
+    assert_eq!(rope.slice_to_string(3..9), "ße, Wo");
+    assert_eq!(rope.slice_to_string(3..=4), "ße");
+    assert_eq!(rope.slice_to_string(7..), "World!");
+    assert_eq!(rope.slice_to_string(4..4), "");
+    assert_eq!(rope.slice_to_string(10..30), "ld!");
+}
+
+#[test]
+fn test_byte_slice() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grü"), Rope::new("ße, World!")]);
+
+    let slice = rope.byte_slice(2..6);
+    assert_eq!(slice, "üß");
+    assert_eq!(slice.len_chars(), 2);
+    assert_eq!(slice.len_bytes(), 4);
+    assert_eq!(rope.byte_slice(9..), "World!");
+    assert_eq!(rope.byte_slice(..), rope.slice(0..rope.len_chars()));
+}
+
+#[test]
+#[should_panic]
+fn test_byte_slice_inside_char() {
+    Rope::new("Grüße").byte_slice(3..5);
+}
+
+#[test]
+fn test_slice_to_rope() {
+    let rope = Rope::from_leaves(vec![Rope::new("Hello"), Rope::new(", World!")]);
+
+    assert_eq!(
+        rope.slice(3..9).to_rope().chars().collect::<String>(),
+        "lo, Wo"
+    );
+    assert_eq!(rope.slice(20..30).to_rope().len(), 0);
+    assert_eq!(
+        rope.slice(7..13).to_rope().chars().collect::<String>(),
+        "World!"
+    );
+}
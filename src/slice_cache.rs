@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::rope::Rope;
+
+/// A small cache of the strings last copied out of a rope, for render loops
+/// that ask for the same slices every frame. Entries are keyed by the version
+/// of the rope and the char range; the version is the tree itself, so any
+/// edit drops all entries without having to tell the cache about it.
+///
+/// The cache keeps the version it was last used with alive until then.
+#[derive(Debug)]
+pub struct SliceCache {
+    version: Option<Rope>,
+    capacity: usize,
+    // most recently used first
+    entries: VecDeque<(Range<usize>, String)>,
+}
+
+impl SliceCache {
+    /// A cache holding up to `capacity` slices.
+    pub fn new(capacity: usize) -> SliceCache {
+        SliceCache {
+            version: None,
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// The chars of `rope` in `range`, clamped like `slice_to_string`. Copied
+    /// out of the rope only if not already cached for this version.
+    pub fn get(&mut self, rope: &Rope, range: Range<usize>) -> &str {
+        // edits never change a tree in place, so the same root is the same text
+        let current = self
+            .version
+            .as_ref()
+            .is_some_and(|v| v.ptr_eq_subtree(rope));
+        if !current {
+            self.entries.clear();
+            self.version = Some(rope.share());
+        }
+
+        match self.entries.iter().position(|(r, _)| *r == range) {
+            Some(i) => {
+                let entry = self.entries.remove(i).expect("position is in bounds");
+                self.entries.push_front(entry);
+            }
+            None => {
+                // the slice returned is always kept, even with no capacity
+                self.entries.truncate(self.capacity.saturating_sub(1));
+                let text = rope.slice_to_string(range.clone());
+                self.entries.push_front((range, text));
+            }
+        }
+        &self.entries[0].1
+    }
+
+    /// Number of slices cached for the current version.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops all entries, and the rope they were copied from.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.version = None;
+    }
+}
+
+#[test]
+fn test_slice_cache() {
+    let mut rope = Rope::new("one\ntwo\nthree\n");
+    let mut cache = SliceCache::new(2);
+
+    let first = cache.get(&rope, 0..3).as_ptr();
+    assert_eq!(cache.get(&rope, 4..7), "two");
+    // a hit returns the string copied the first time
+    assert_eq!(cache.get(&rope, 0..3).as_ptr(), first);
+    assert_eq!(cache.len(), 2);
+
+    // the least recently used slice makes room
+    assert_eq!(cache.get(&rope, 8..20), "three\n");
+    assert_eq!(cache.get(&rope, 0..3).as_ptr(), first);
+    assert_eq!(cache.len(), 2);
+
+    // an edit is a new version
+    rope.insert(0, "zero\n");
+    assert_eq!(cache.get(&rope, 0..3), "zer");
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get(&rope.share(), 0..3), "zer");
+    assert_eq!(cache.len(), 1);
+
+    let mut unused = SliceCache::new(0);
+    assert_eq!(unused.get(&rope, 5..8), "one");
+    cache.clear();
+    assert!(cache.is_empty());
+}
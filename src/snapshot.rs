@@ -0,0 +1,126 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::rope::Rope;
+
+/// A leaf's text, kept alive by its own handle on the leaf buffer.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    buf: Arc<String>,
+    start: usize,
+    end: usize,
+}
+
+impl Chunk {
+    pub fn as_str(&self) -> &str {
+        &self.buf[self.start..self.end]
+    }
+}
+
+impl Deref for Chunk {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Rope {
+    /// Like `chunks`, but the iterator pins the version of the rope it started
+    /// on instead of borrowing it. It is `Send + 'static`, so a background
+    /// search can keep walking it while the document moves on to new
+    /// versions.
+    pub fn snapshot_chunks(&self) -> SnapshotChunks {
+        SnapshotChunks {
+            stack: vec![Arc::new(self.share())],
+        }
+    }
+
+    /// Like `chars`, pinned to the current version of the rope.
+    pub fn snapshot_chars(&self) -> SnapshotChars {
+        SnapshotChars {
+            chunks: self.snapshot_chunks(),
+            cur: None,
+            pos: 0,
+        }
+    }
+}
+
+pub struct SnapshotChunks {
+    stack: Vec<Arc<Rope>>,
+}
+
+impl Iterator for SnapshotChunks {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(rope) = self.stack.pop() {
+            match rope.as_ref() {
+                Rope::Leaf(leaf) => {
+                    return Some(Chunk {
+                        buf: Arc::clone(&leaf.buf),
+                        start: leaf.start,
+                        end: leaf.end,
+                    })
+                }
+                Rope::Node(node) => {
+                    if let Some(right) = node.right.as_ref() {
+                        self.stack.push(Arc::clone(right));
+                    }
+                    if let Some(left) = node.left.as_ref() {
+                        self.stack.push(Arc::clone(left));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+pub struct SnapshotChars {
+    chunks: SnapshotChunks,
+    cur: Option<Chunk>,
+    // byte offset of the next char in `cur`
+    pos: usize,
+}
+
+impl Iterator for SnapshotChars {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chunk) = self.cur.as_ref() {
+                if let Some(c) = chunk[self.pos..].chars().next() {
+                    self.pos += c.len_utf8();
+                    return Some(c);
+                }
+            }
+            self.cur = Some(self.chunks.next()?);
+            self.pos = 0;
+        }
+    }
+}
+
+#[test]
+fn test_snapshot_chars_outlive_edits() {
+    let rope = Rope::from_leaves(vec![Rope::new("Grüße, "), Rope::new("World!")]);
+    let chars = rope.snapshot_chars();
+
+    let mut rope = rope;
+    rope.insert(9, "Cruel ");
+    drop(rope);
+
+    let handle = std::thread::spawn(move || chars.collect::<String>());
+    assert_eq!(handle.join().unwrap(), "Grüße, World!");
+}
+
+#[test]
+fn test_snapshot_chunks() {
+    let rope = Rope::from_leaves(vec![Rope::new("Hello,"), Rope::new(" World!")]);
+    let chunks: Vec<Chunk> = rope.snapshot_chunks().collect();
+    drop(rope);
+
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(&*chunks[0], "Hello,");
+    assert_eq!(chunks[1].as_str(), " World!");
+}
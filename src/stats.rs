@@ -0,0 +1,101 @@
+use std::ops::{Range, RangeBounds};
+
+use crate::rope::Rope;
+
+/// Size of a document, as shown in the status bar of an editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    pub bytes: usize,
+    pub chars: usize,
+    pub lines: usize,
+}
+
+impl Stats {
+    /// Updates the counters for an edit instead of recomputing them.
+    pub fn apply(&mut self, edit: &EditInfo) {
+        self.bytes = self.bytes - edit.bytes_removed + edit.bytes_added;
+        self.chars = self.chars - edit.chars_removed + edit.chars_added;
+        self.lines = self.lines - edit.lines_removed + edit.lines_added;
+    }
+}
+
+/// What an edit made by `Rope::edit` changed. Lines added and removed count
+/// line breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditInfo {
+    /// The byte range that was replaced, in the rope before the edit.
+    pub range: Range<usize>,
+    /// The same range in chars.
+    pub char_range: Range<usize>,
+    pub bytes_added: usize,
+    pub bytes_removed: usize,
+    pub chars_added: usize,
+    pub chars_removed: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl Rope {
+    /// Size of the rope. All three counts are cached, so this is O(1).
+    pub fn stats(&self) -> Stats {
+        Stats {
+            bytes: self.len_bytes(),
+            chars: self.len_chars(),
+            lines: self.len_lines(),
+        }
+    }
+
+    /// Replaces the bytes in `range` with `s` like `replace_range`, and
+    /// reports how much text the edit added and removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics on the same ranges as `remove`.
+    pub fn edit<R: RangeBounds<usize>>(&mut self, range: R, s: &str) -> EditInfo {
+        let range = self.byte_range(range);
+        let (l, r) = self.split(range.start);
+        let (removed, r) = r.split(range.len());
+        let inserted = Rope::chunked(s);
+        let info = EditInfo {
+            char_range: self.byte_to_char(range.start)..self.byte_to_char(range.end),
+            range,
+            bytes_added: inserted.len(),
+            bytes_removed: removed.len(),
+            chars_added: inserted.len_chars(),
+            chars_removed: removed.len_chars(),
+            lines_added: inserted.newlines(),
+            lines_removed: removed.newlines(),
+        };
+        *self = Rope::concat(Rope::concat(l, inserted), r);
+        info
+    }
+}
+
+#[test]
+fn test_edit_info() {
+    let mut rope = Rope::new("one\ntwo\nthree");
+    let mut stats = rope.stats();
+    assert_eq!(
+        stats,
+        Stats {
+            bytes: 13,
+            chars: 13,
+            lines: 3
+        }
+    );
+
+    let info = rope.edit(3..8, " → ");
+    assert_eq!(info.range, 3..8);
+    assert_eq!(info.char_range, 3..8);
+    assert_eq!((info.bytes_removed, info.bytes_added), (5, 5));
+    assert_eq!((info.chars_removed, info.chars_added), (5, 3));
+    assert_eq!((info.lines_removed, info.lines_added), (2, 0));
+    stats.apply(&info);
+    assert_eq!(stats, rope.stats());
+    assert_eq!(rope.to_string(), "one → three");
+
+    let info = rope.edit(rope.len().., "\nfour\n");
+    stats.apply(&info);
+    assert_eq!(stats, rope.stats());
+    assert_eq!(stats.lines, 3);
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::balance::MAX_DEPTH;
+use crate::rope::{Leaf, Rope};
+
+const MAGIC: &[u8; 4] = b"RPS1";
+
+const TAG_LEAF: u8 = 0;
+const TAG_NODE: u8 = 1;
+
+enum Record {
+    Leaf {
+        buf: usize,
+        start: usize,
+        end: usize,
+    },
+    Node {
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Rope {
+    /// Writes the rope along with its layout: every distinct leaf buffer is
+    /// stored once, followed by the tree with shared subtrees stored once.
+    /// Reading it back with `read_structure` restores the same leaf
+    /// boundaries and the same sharing. The output only depends on the
+    /// structure of the rope.
+    pub fn write_structure<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        write_trees(&mut writer, &[self])?;
+        Ok(())
+    }
+
+    /// Reads a rope written by `write_structure`. Malformed input is reported
+    /// as an `InvalidData` error, and so is a tree deeper than `MAX_DEPTH`,
+    /// which no edit produces.
+    pub fn read_structure<R: Read>(mut reader: R) -> io::Result<Rope> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a rope structure"));
+        }
+        let mut trees = read_trees(&mut reader)?;
+        let root = trees.pop().ok_or_else(|| invalid("no root"))?;
+        Ok(unshare(root))
+    }
+}
+
+// writes the buffers and subtrees of all the `roots`, anything shared between
+// them once, and returns the index of each root among the subtrees
+pub(crate) fn write_trees<W: Write>(writer: &mut W, roots: &[&Rope]) -> io::Result<Vec<usize>> {
+    let mut buffers: Vec<&Arc<String>> = Vec::new();
+    let mut buffer_ids: HashMap<*const String, usize> = HashMap::new();
+    let mut records = Vec::new();
+    let mut ids: HashMap<*const Rope, usize> = HashMap::new();
+
+    // post-order without recursion, children get their ids first
+    let mut stack: Vec<(&Rope, bool)> = roots.iter().rev().map(|&root| (root, false)).collect();
+    while let Some((rope, expanded)) = stack.pop() {
+        if ids.contains_key(&(rope as *const Rope)) {
+            continue;
+        }
+        let record = match rope {
+            Rope::Leaf(leaf) => {
+                let buf = *buffer_ids.entry(Arc::as_ptr(&leaf.buf)).or_insert_with(|| {
+                    buffers.push(&leaf.buf);
+                    buffers.len() - 1
+                });
+                Record::Leaf {
+                    buf,
+                    start: leaf.start,
+                    end: leaf.end,
+                }
+            }
+            Rope::Node(node) => {
+                let left = node.left.as_deref().expect("left child cannot be empty");
+                let right = node.right.as_deref().expect("right child cannot be empty");
+                if !expanded {
+                    stack.push((rope, true));
+                    stack.push((right, false));
+                    stack.push((left, false));
+                    continue;
+                }
+                Record::Node {
+                    left: ids[&(left as *const Rope)],
+                    right: ids[&(right as *const Rope)],
+                }
+            }
+        };
+        ids.insert(rope, records.len());
+        records.push(record);
+    }
+
+    write_usize(writer, buffers.len())?;
+    for buf in buffers {
+        write_usize(writer, buf.len())?;
+        writer.write_all(buf.as_bytes())?;
+    }
+    write_usize(writer, records.len())?;
+    for record in records {
+        match record {
+            Record::Leaf { buf, start, end } => {
+                writer.write_all(&[TAG_LEAF])?;
+                write_usize(writer, buf)?;
+                write_usize(writer, start)?;
+                write_usize(writer, end)?;
+            }
+            Record::Node { left, right } => {
+                writer.write_all(&[TAG_NODE])?;
+                write_usize(writer, left)?;
+                write_usize(writer, right)?;
+            }
+        }
+    }
+    Ok(roots
+        .iter()
+        .map(|&root| ids[&(root as *const Rope)])
+        .collect())
+}
+
+// reads back every subtree written by `write_trees`, in order
+pub(crate) fn read_trees<R: Read>(reader: &mut R) -> io::Result<Vec<Arc<Rope>>> {
+    let mut buffers = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        let len = read_usize(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let buf = String::from_utf8(bytes).map_err(|_| invalid("buffer is not UTF-8"))?;
+        buffers.push(Arc::new(buf));
+    }
+
+    let mut ropes: Vec<Arc<Rope>> = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+        let rope = match tag[0] {
+            TAG_LEAF => {
+                let buf = buffers
+                    .get(read_usize(reader)?)
+                    .ok_or_else(|| invalid("unknown buffer"))?;
+                let start = read_usize(reader)?;
+                let end = read_usize(reader)?;
+                if start > end || buf.get(start..end).is_none() {
+                    return Err(invalid("leaf out of its buffer"));
+                }
+                Rope::Leaf(Leaf::view(Arc::clone(buf), start, end))
+            }
+            TAG_NODE => {
+                let left = read_usize(reader)?;
+                let right = read_usize(reader)?;
+                let (left, right) = match (ropes.get(left), ropes.get(right)) {
+                    (Some(left), Some(right)) => (Arc::clone(left), Arc::clone(right)),
+                    _ => return Err(invalid("node refers to an unknown subtree")),
+                };
+                // checked before the node exists, so a hostile chain is
+                // refused long before it could overflow the stack
+                if 1 + left.depth().max(right.depth()) > MAX_DEPTH {
+                    return Err(invalid("tree too deep"));
+                }
+                // shared subtrees double the length at every level
+                match left.len().checked_add(right.len()) {
+                    Some(len) if len <= Rope::MAX_LEN => {}
+                    _ => return Err(invalid("rope too long")),
+                }
+                Rope::join(left, right)
+            }
+            _ => return Err(invalid("unknown record")),
+        };
+        ropes.push(Arc::new(rope));
+    }
+    Ok(ropes)
+}
+
+// a rope out of a subtree read back, which may be shared with other trees
+pub(crate) fn unshare(rope: Arc<Rope>) -> Rope {
+    Arc::try_unwrap(rope).unwrap_or_else(|rope| rope.share())
+}
+
+pub(crate) fn write_usize<W: Write>(writer: &mut W, n: usize) -> io::Result<()> {
+    writer.write_all(&(n as u64).to_le_bytes())
+}
+
+pub(crate) fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| invalid("length out of range"))
+}
+
+pub(crate) fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[test]
+fn test_structure_roundtrip() {
+    let rope = Rope::from_leaves(vec![
+        Rope::new("one\n"),
+        Rope::new("two\n"),
+        Rope::new("three"),
+    ]);
+    let rope = rope.duplicate_lines(0..2);
+
+    let mut saved = Vec::new();
+    rope.write_structure(&mut saved).unwrap();
+    let restored = Rope::read_structure(saved.as_slice()).unwrap();
+
+    assert_eq!(
+        restored.chars().collect::<String>(),
+        "one\ntwo\none\ntwo\nthree"
+    );
+    let chunks: Vec<&str> = restored.chunks().collect();
+    assert_eq!(chunks, rope.chunks().collect::<Vec<&str>>());
+    assert_eq!(restored.depth(), rope.depth());
+
+    let mut again = Vec::new();
+    restored.write_structure(&mut again).unwrap();
+    assert_eq!(again, saved);
+}
+
+#[test]
+fn test_structure_keeps_sharing() {
+    let leaf = Arc::new(Rope::new("shared"));
+    let pair = Arc::new(Rope::join(Arc::clone(&leaf), Arc::clone(&leaf)));
+    let rope = Rope::join(Arc::clone(&pair), pair);
+
+    let mut saved = Vec::new();
+    rope.write_structure(&mut saved).unwrap();
+    let restored = Rope::read_structure(saved.as_slice()).unwrap();
+
+    let Rope::Node(root) = &restored else {
+        panic!("root should be a node")
+    };
+    let (left, right) = (root.left.as_ref().unwrap(), root.right.as_ref().unwrap());
+    assert!(Arc::ptr_eq(left, right));
+    let Rope::Node(pair) = left.as_ref() else {
+        panic!("pair should be a node")
+    };
+    assert!(Arc::ptr_eq(
+        pair.left.as_ref().unwrap(),
+        pair.right.as_ref().unwrap()
+    ));
+    assert_eq!(restored.chars().collect::<String>(), "shared".repeat(4));
+}
+
+#[test]
+fn test_structure_rejects_garbage() {
+    let mut saved = Vec::new();
+    Rope::new("héllo").write_structure(&mut saved).unwrap();
+
+    assert!(Rope::read_structure(&saved[..saved.len() - 1]).is_err());
+
+    // leaf ending inside the two bytes of 'é'
+    let end = saved.len() - 8;
+    saved[end] = 2;
+    let err = Rope::read_structure(saved.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    assert!(Rope::read_structure(&b"nope"[..]).is_err());
+}
+
+#[test]
+fn test_structure_rejects_deep_trees() {
+    // one leaf, then a chain of nodes each holding the previous one
+    let mut hostile = MAGIC.to_vec();
+    let chain = 200_000;
+    for n in [1, 1] {
+        write_usize(&mut hostile, n).unwrap();
+    }
+    hostile.push(b'a');
+    write_usize(&mut hostile, chain + 1).unwrap();
+    hostile.push(TAG_LEAF);
+    for n in [0, 0, 1] {
+        write_usize(&mut hostile, n).unwrap();
+    }
+    for i in 0..chain {
+        hostile.push(TAG_NODE);
+        for n in [i, 0] {
+            write_usize(&mut hostile, n).unwrap();
+        }
+    }
+
+    let err = Rope::read_structure(hostile.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_structure_rejects_long_trees() {
+    // one leaf, then nodes each holding the previous one twice
+    let mut hostile = MAGIC.to_vec();
+    for n in [1, 2] {
+        write_usize(&mut hostile, n).unwrap();
+    }
+    hostile.extend(b"ab");
+    write_usize(&mut hostile, 63).unwrap();
+    hostile.push(TAG_LEAF);
+    for n in [0, 0, 2] {
+        write_usize(&mut hostile, n).unwrap();
+    }
+    for i in 0..62 {
+        hostile.push(TAG_NODE);
+        for n in [i, i] {
+            write_usize(&mut hostile, n).unwrap();
+        }
+    }
+
+    let err = Rope::read_structure(hostile.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+/// A rewrite applied to text as it streams into a `RopeBuilder`, so a file
+/// is normalized while it is read instead of rewritten afterwards. Text
+/// comes in arbitrary pieces; state carried between them, such as the
+/// current column, lives in the transform.
+pub trait Transform: fmt::Debug + Send {
+    /// Rewrites the next piece of text into `out`.
+    fn push(&mut self, text: &str, out: &mut String);
+
+    /// Writes out anything held back once the text has ended.
+    fn finish(&mut self, _out: &mut String) {}
+}
+
+/// Drops a byte order mark at the very start of the text.
+#[derive(Debug, Default)]
+pub struct StripBom {
+    started: bool,
+}
+
+impl Transform for StripBom {
+    fn push(&mut self, text: &str, out: &mut String) {
+        match self.started {
+            true => out.push_str(text),
+            false => out.push_str(text.strip_prefix('\u{feff}').unwrap_or(text)),
+        }
+        self.started |= !text.is_empty();
+    }
+}
+
+/// Turns `\r\n` and lone `\r` line breaks into `\n`.
+#[derive(Debug, Default)]
+pub struct NormalizeLineEndings {
+    // the last char was a `\r`, so a `\n` right after it is already written
+    after_cr: bool,
+}
+
+impl Transform for NormalizeLineEndings {
+    fn push(&mut self, text: &str, out: &mut String) {
+        for c in text.chars() {
+            match c {
+                '\r' => out.push('\n'),
+                '\n' if self.after_cr => {}
+                c => out.push(c),
+            }
+            self.after_cr = c == '\r';
+        }
+    }
+}
+
+/// Replaces tabs with spaces up to the next tab stop, every `width`
+/// columns. Columns count chars.
+#[derive(Debug)]
+pub struct ExpandTabs {
+    width: usize,
+    column: usize,
+}
+
+impl ExpandTabs {
+    pub fn new(width: usize) -> ExpandTabs {
+        ExpandTabs {
+            width: width.max(1),
+            column: 0,
+        }
+    }
+}
+
+impl Transform for ExpandTabs {
+    fn push(&mut self, text: &str, out: &mut String) {
+        for c in text.chars() {
+            match c {
+                '\t' => {
+                    let pad = self.width - self.column % self.width;
+                    out.extend(std::iter::repeat_n(' ', pad));
+                    self.column += pad;
+                }
+                '\n' => {
+                    out.push(c);
+                    self.column = 0;
+                }
+                c => {
+                    out.push(c);
+                    self.column += 1;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_transforms() {
+    fn run(transform: &mut dyn Transform, pieces: &[&str]) -> String {
+        let mut out = String::new();
+        for piece in pieces {
+            transform.push(piece, &mut out);
+        }
+        transform.finish(&mut out);
+        out
+    }
+
+    assert_eq!(
+        run(&mut StripBom::default(), &["\u{feff}a", "\u{feff}"]),
+        "a\u{feff}"
+    );
+    assert_eq!(run(&mut StripBom::default(), &["", "\u{feff}a"]), "a");
+    assert_eq!(
+        run(
+            &mut NormalizeLineEndings::default(),
+            &["a\r\nb\r", "\nc\rd\n"]
+        ),
+        "a\nb\nc\nd\n"
+    );
+    assert_eq!(
+        run(&mut ExpandTabs::new(4), &["a\tbc", "\t|\n\t|"]),
+        "a   bc  |\n    |"
+    );
+}
@@ -0,0 +1,126 @@
+use crate::rope::{Chars, Rope};
+
+impl Rope {
+    /// Length of the rope in UTF-16 code units, as used by Windows APIs and
+    /// LSP. Cached per node, so this is O(1).
+    pub fn len_utf16(&self) -> usize {
+        match self {
+            Rope::Leaf(leaf) => leaf.utf16,
+            Rope::Node(node) => node.utf16,
+        }
+    }
+
+    /// Iterates over the UTF-16 code units of the rope.
+    pub fn utf16_units(&self) -> Utf16Units<'_> {
+        Utf16Units {
+            chars: self.chars(),
+            low: None,
+        }
+    }
+
+    /// Converts a char offset to a UTF-16 offset. Offsets past the end are
+    /// clamped to the UTF-16 length of the rope.
+    pub fn char_to_utf16(&self, char_idx: usize) -> usize {
+        if self.is_ascii() {
+            return char_idx.min(self.len());
+        }
+        match self {
+            Rope::Leaf(leaf) => leaf
+                .as_str()
+                .chars()
+                .take(char_idx)
+                .map(char::len_utf16)
+                .sum(),
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                let left_chars = left.len_chars();
+                if char_idx < left_chars {
+                    return left.char_to_utf16(char_idx);
+                }
+                left.len_utf16()
+                    + node
+                        .right
+                        .as_ref()
+                        .map_or(0, |r| r.char_to_utf16(char_idx - left_chars))
+            }
+        }
+    }
+
+    /// Converts a UTF-16 offset to the offset of the char containing it.
+    /// Offsets past the end are clamped to the length of the rope in chars.
+    pub fn utf16_to_char(&self, utf16_idx: usize) -> usize {
+        if self.is_ascii() {
+            return utf16_idx.min(self.len());
+        }
+        match self {
+            Rope::Leaf(leaf) => {
+                let mut units = 0;
+                for (i, c) in leaf.as_str().chars().enumerate() {
+                    units += c.len_utf16();
+                    if units > utf16_idx {
+                        return i;
+                    }
+                }
+                self.len_chars()
+            }
+            Rope::Node(node) => {
+                let left = node.left.as_ref().expect("left child cannot be empty");
+                if utf16_idx < left.len_utf16() {
+                    return left.utf16_to_char(utf16_idx);
+                }
+                left.len_chars()
+                    + node
+                        .right
+                        .as_ref()
+                        .map_or(0, |r| r.utf16_to_char(utf16_idx - left.len_utf16()))
+            }
+        }
+    }
+}
+
+pub struct Utf16Units<'a> {
+    chars: Chars<'a>,
+    // second half of a surrogate pair
+    low: Option<u16>,
+}
+
+impl<'a> Iterator for Utf16Units<'a> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(low) = self.low.take() {
+            return Some(low);
+        }
+        let mut buf = [0; 2];
+        let units = self.chars.next()?.encode_utf16(&mut buf);
+        if units.len() == 2 {
+            self.low = Some(units[1]);
+        }
+        Some(units[0])
+    }
+}
+
+#[test]
+fn test_utf16_units() {
+    let rope = Rope::from_leaves(vec![Rope::new("a😀"), Rope::new("é")]);
+
+    assert_eq!(rope.len_utf16(), 4);
+    let units: Vec<u16> = rope.utf16_units().collect();
+    assert_eq!(units, "a😀é".encode_utf16().collect::<Vec<u16>>());
+}
+
+#[test]
+fn test_utf16_conversion() {
+    let rope = Rope::from_leaves(vec![Rope::new("a😀"), Rope::new("éb")]);
+
+    assert_eq!(rope.char_to_utf16(1), 1);
+    assert_eq!(rope.char_to_utf16(2), 3);
+    assert_eq!(rope.char_to_utf16(3), 4);
+    assert_eq!(rope.char_to_utf16(10), 5);
+
+    assert_eq!(rope.utf16_to_char(1), 1);
+    assert_eq!(rope.utf16_to_char(2), 1);
+    assert_eq!(rope.utf16_to_char(3), 2);
+    assert_eq!(rope.utf16_to_char(4), 3);
+    assert_eq!(rope.utf16_to_char(10), 4);
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+
+use crate::rope::Rope;
+
+/// Identifies the content of a rope at some point, for optimistic
+/// concurrency: a writer remembers the version it read and only gets to edit
+/// if nobody else did in the meantime.
+///
+/// A version holds on to the tree it was taken from, so taking one is O(1)
+/// and comparing two is as well: edits never change a tree in place, and the
+/// tree can't be freed and its memory reused while the version exists.
+#[derive(Debug, Clone)]
+pub struct Version(pub(crate) Rope);
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool {
+        self.0.ptr_eq_subtree(&other.0)
+    }
+}
+
+impl Eq for Version {}
+
+/// The rope was edited since the version an edit was based on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// The version the rope is at now.
+    pub current: Version,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the rope was edited since the expected version")
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+impl Rope {
+    /// The current version of the rope. Any edit after this makes a new one.
+    pub fn version(&self) -> Version {
+        Version(self.share())
+    }
+
+    /// Applies `op` only if the rope is still at `expected`, and returns what
+    /// `op` returned. Otherwise leaves the rope alone and reports the version
+    /// it is at, so the writer can rebase its edit and try again.
+    pub fn try_apply_if_version<T, F: FnOnce(&mut Rope) -> T>(
+        &mut self,
+        op: F,
+        expected: &Version,
+    ) -> Result<T, VersionMismatch> {
+        if !self.ptr_eq_subtree(&expected.0) {
+            return Err(VersionMismatch {
+                current: self.version(),
+            });
+        }
+        Ok(op(self))
+    }
+}
+
+#[test]
+fn test_try_apply_if_version() {
+    let mut rope = Rope::new("Hello, World!");
+    let read = rope.version();
+    assert_eq!(read, rope.clone().version());
+
+    // two writers based on the same version, the second one loses
+    let len = rope
+        .try_apply_if_version(
+            |rope| {
+                rope.insert(5, " there");
+                rope.len()
+            },
+            &read,
+        )
+        .unwrap();
+    assert_eq!(len, 19);
+    let err = rope
+        .try_apply_if_version(|rope| rope.insert(0, "Oh, "), &read)
+        .unwrap_err();
+    assert_eq!(rope.to_string(), "Hello there, World!");
+    assert_eq!(err.current, rope.version());
+    assert_ne!(err.current, read);
+
+    // rebased on the current version it goes through
+    rope.try_apply_if_version(|rope| rope.insert(0, "Oh, "), &err.current)
+        .unwrap();
+    assert_eq!(rope.to_string(), "Oh, Hello there, World!");
+
+    // equal content is not enough, it has to be the same tree
+    let copy = Rope::new(&rope.to_string());
+    assert_ne!(copy.version(), rope.version());
+}
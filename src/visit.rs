@@ -0,0 +1,138 @@
+use std::ops::ControlFlow;
+
+use crate::rope::Rope;
+
+/// Walks the tree of a rope, see `Rope::visit`.
+///
+/// Subtrees are handed over as ropes of their own, so their cached metrics
+/// (`len_chars`, `len_lines`, `flags`, ...) can be used to answer a question
+/// about a whole subtree without descending into it.
+pub trait Visitor {
+    /// Called on every inner node before its children. Returning `false`
+    /// skips the subtree.
+    fn visit_node(&mut self, _node: &Rope) -> bool {
+        true
+    }
+
+    /// Called on every leaf, from left to right.
+    fn visit_leaf(&mut self, text: &str);
+}
+
+impl Rope {
+    /// Folds over the chunks of the rope from left to right.
+    pub fn fold<B, F>(&self, init: B, f: F) -> B
+    where
+        F: FnMut(B, &str) -> B,
+    {
+        self.chunks().fold(init, f)
+    }
+
+    /// Like `fold`, but stops at the first chunk for which `f` fails.
+    pub fn try_fold<B, E, F>(&self, init: B, f: F) -> Result<B, E>
+    where
+        F: FnMut(B, &str) -> Result<B, E>,
+    {
+        self.chunks().try_fold(init, f)
+    }
+
+    /// Calls `f` on the chunks from left to right until it breaks, and
+    /// returns the value it broke with. Chunks past that point are not read.
+    pub fn for_each_chunk_until<B, F>(&self, f: F) -> Option<B>
+    where
+        F: FnMut(&str) -> ControlFlow<B>,
+    {
+        match self.chunks().try_for_each(f) {
+            ControlFlow::Break(b) => Some(b),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Walks the tree depth first, left to right.
+    pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+        let mut stack = vec![self];
+        while let Some(rope) = stack.pop() {
+            match rope {
+                Rope::Leaf(leaf) => visitor.visit_leaf(leaf.as_str()),
+                Rope::Node(node) => {
+                    if visitor.visit_node(rope) {
+                        stack.extend(node.right.as_deref());
+                        stack.extend(node.left.as_deref());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fold() {
+    let rope = Rope::from_leaves(vec![Rope::new("one two"), Rope::new(" three")]);
+    let spaces = rope.fold(0, |n, chunk| n + chunk.matches(' ').count());
+    assert_eq!(spaces, 2);
+}
+
+#[test]
+fn test_try_fold_stops_early() {
+    let rope = Rope::from_leaves(vec![Rope::new("ab"), Rope::new("c\u{0}"), Rope::new("d")]);
+
+    let mut seen = 0;
+    let res: Result<usize, usize> = rope.try_fold(0, |len, chunk| {
+        seen += 1;
+        match chunk.find('\u{0}') {
+            Some(at) => Err(len + at),
+            None => Ok(len + chunk.len()),
+        }
+    });
+    assert_eq!(res, Err(3));
+    assert_eq!(seen, 2);
+    assert_eq!(rope.try_fold(0, |n, c| Ok::<_, ()>(n + c.len())), Ok(5));
+
+    let mut seen = 0;
+    let first_c = rope.for_each_chunk_until(|chunk| {
+        seen += 1;
+        match chunk.contains('c') {
+            true => ControlFlow::Break(chunk.to_string()),
+            false => ControlFlow::Continue(()),
+        }
+    });
+    assert_eq!(first_c.as_deref(), Some("c\u{0}"));
+    assert_eq!(seen, 2);
+    assert_eq!(
+        rope.for_each_chunk_until(|_| ControlFlow::<()>::Continue(())),
+        None
+    );
+}
+
+#[test]
+fn test_visitor_skips_subtrees() {
+    // counts tabs, skipping the subtrees known to hold none
+    struct Tabs {
+        count: usize,
+        leaves: usize,
+    }
+
+    impl Visitor for Tabs {
+        fn visit_node(&mut self, node: &Rope) -> bool {
+            node.has_tabs()
+        }
+
+        fn visit_leaf(&mut self, text: &str) {
+            self.leaves += 1;
+            self.count += text.matches('\t').count();
+        }
+    }
+
+    let rope = Rope::from_leaves(vec![
+        Rope::new("\ta"),
+        Rope::new("b\t"),
+        Rope::new("c"),
+        Rope::new("d"),
+    ]);
+    let mut tabs = Tabs {
+        count: 0,
+        leaves: 0,
+    };
+    rope.visit(&mut tabs);
+    assert_eq!(tabs.count, 2);
+    assert_eq!(tabs.leaves, 2);
+}